@@ -0,0 +1,104 @@
+const AIR_COLOR = vec4<f32>(0.02, 0.02, 0.02, 1.0);
+const SAND_COLOR = vec4<f32>(0.8, 0.8, 0.2, 1.0);
+const WATER_COLOR = vec4<f32>(0.2, 0.2, 0.8, 1.0);
+const ROCK_COLOR = vec4<f32>(0.4, 0.4, 0.4, 1.0);
+
+// keep in sync with DebugView::mode
+const VIEW_ELEMENTS = 1u;
+const VIEW_CHANGES = 2u;
+const VIEW_CHUNKS = 3u;
+
+const CHUNK_SIZE = 32u;
+// alpha kept every frame by the highlight of a changed cell
+const CHANGE_FADE = 0.9;
+const CHANGE_COLOR = vec3<f32>(1.0, 0.2, 0.1);
+const ACTIVE_CHUNK_COLOR = vec4<f32>(0.2, 1.0, 0.3, 0.9);
+const QUIET_CHUNK_COLOR = vec4<f32>(0.5, 0.5, 0.5, 0.25);
+
+struct PushConstants {
+    view: u32,
+    // 1 on the first frame of a view, when the overlay and previous world are stale
+    reset: u32,
+}
+var<push_constant> pc: PushConstants;
+
+@group(0) @binding(0)
+var texture: texture_storage_2d<rgba8unorm, read_write>;
+
+@group(0) @binding(1)
+var overlay: texture_storage_2d<rgba8unorm, read_write>;
+
+// the world as it was when the overlay last ran
+@group(0) @binding(2)
+var previous: texture_storage_2d<rgba8unorm, read_write>;
+
+// 1 for every chunk with a changed cell, cleared before every frame
+@group(0) @binding(3)
+var<storage, read_write> chunk_activity: array<atomic<u32>>;
+
+fn compare_vectors(v1: vec4<f32>, v2: vec4<f32>) -> bool {
+    return distance(v1, v2) < 0.01;
+}
+
+fn chunk_index(location: vec2<u32>) -> u32 {
+    let chunks_x = (u32(textureDimensions(texture).x) + CHUNK_SIZE - 1u) / CHUNK_SIZE;
+    let chunk = location / CHUNK_SIZE;
+    return chunk.y * chunks_x + chunk.x;
+}
+
+fn false_color(color: vec4<f32>) -> vec4<f32> {
+    if(compare_vectors(color, AIR_COLOR)){
+        return vec4<f32>(0.0, 0.0, 0.0, 0.6);
+    }else if(compare_vectors(color, SAND_COLOR)){
+        return vec4<f32>(1.0, 0.5, 0.0, 1.0);
+    }else if(compare_vectors(color, WATER_COLOR)){
+        return vec4<f32>(0.0, 1.0, 1.0, 1.0);
+    }else if(compare_vectors(color, ROCK_COLOR)){
+        return vec4<f32>(0.6, 0.2, 1.0, 1.0);
+    }
+    // no element has this color, something wrote garbage
+    return vec4<f32>(1.0, 0.0, 1.0, 1.0);
+}
+
+// Compares the world with the previous frame, marking changed cells and their chunks
+@compute @workgroup_size(8, 8, 1)
+fn track(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let location = vec2<i32>(invocation_id.xy);
+    let current = textureLoad(texture, location);
+    let changed = pc.reset == 0u && !compare_vectors(current, textureLoad(previous, location));
+    textureStore(previous, location, current);
+
+    if (changed) {
+        atomicStore(&chunk_activity[chunk_index(invocation_id.xy)], 1u);
+    }
+
+    if (pc.view == VIEW_CHANGES) {
+        var alpha = 0.0;
+        if (changed) {
+            alpha = 1.0;
+        } else if (pc.reset == 0u) {
+            alpha = textureLoad(overlay, location).a * CHANGE_FADE;
+        }
+        textureStore(overlay, location, vec4<f32>(CHANGE_COLOR, alpha));
+    }
+}
+
+// Paints the overlay for the views that do not accumulate over frames
+@compute @workgroup_size(8, 8, 1)
+fn draw(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let location = vec2<i32>(invocation_id.xy);
+
+    if (pc.view == VIEW_ELEMENTS) {
+        textureStore(overlay, location, false_color(textureLoad(texture, location)));
+    } else if (pc.view == VIEW_CHUNKS) {
+        let in_chunk = invocation_id.xy % CHUNK_SIZE;
+        let border = in_chunk.x == 0u || in_chunk.y == 0u;
+        var color = vec4<f32>(0.0);
+        if (border && atomicLoad(&chunk_activity[chunk_index(invocation_id.xy)]) != 0u) {
+            color = ACTIVE_CHUNK_COLOR;
+        } else if (border) {
+            color = QUIET_CHUNK_COLOR;
+        }
+        textureStore(overlay, location, color);
+    }
+}
@@ -3,44 +3,230 @@ const SAND_COLOR = vec4<f32>(0.8, 0.8, 0.2, 1.0);
 const WATER_COLOR = vec4<f32>(0.2, 0.2, 0.8, 1.0);
 const ROCK_COLOR = vec4<f32>(0.4, 0.4, 0.4, 1.0);
 
+const SHAPE_SEGMENT = 0u;
+const SHAPE_RECTANGLE = 1u;
+const SHAPE_ELLIPSE = 2u;
+
+const MATERIAL_SINGLE = 0u;
+const MATERIAL_MIX = 1u;
+const MATERIAL_CHECKER = 2u;
+const MATERIAL_STRIPES = 3u;
+
+const MODE_OVERWRITE = 0u;
+const MODE_ONLY_EMPTY = 1u;
+const MODE_REPLACE_ONLY = 2u;
+const MODE_PROTECT = 3u;
+
+const NO_ELEMENT = 255u;
+
 struct PushConstants {
     draw_start: vec2<f32>,
     draw_end: vec2<f32>,
     draw_radius: f32,
     element: u32,
+    shape: u32,
+    hollow: u32,
+    material: u32,
+    // element indices, one byte each
+    material_elements: u32,
+    // cumulative mix weights in 0..=255, one byte each
+    material_weights: u32,
+    material_count: u32,
+    material_scale: u32,
+    seed: u32,
+    mode: u32,
+    mode_element: u32,
 }
 var<push_constant> pc: PushConstants;
 
 @group(0) @binding(0)
 var texture: texture_storage_2d<rgba8unorm, read_write>;
 
+// edits made through PixelWorldCommands, drawn one after the other by draw_edits
+@group(1) @binding(0)
+var<storage, read> edits: array<PushConstants>;
+
+// the shape being drawn, pc or one of the edits
+var<private> shape: PushConstants;
+// color of the cell of this invocation, stored once every shape is drawn
+var<private> cell: vec4<f32>;
+var<private> painted: bool;
+
 @compute @workgroup_size(8, 8, 1)
 fn draw(@builtin(global_invocation_id) invocation_id: vec3<u32>)
 {
     let pixel = vec2<u32>(invocation_id.xy);
     let size = vec2<u32>(textureDimensions(texture));
-    if (pixel.x >= size.x && pixel.y >= size.y) {
+    if (pixel.x >= size.x || pixel.y >= size.y) {
+        return ;
+    }
+
+    cell = textureLoad(texture, vec2<i32>(pixel));
+    shape = pc;
+    draw_shape(pixel);
+    if (painted) {
+        textureStore(texture, vec2<i32>(pixel), cell);
+    }
+}
+
+// Draws every edit in one pass over the world, in the order they were made
+@compute @workgroup_size(8, 8, 1)
+fn draw_edits(@builtin(global_invocation_id) invocation_id: vec3<u32>)
+{
+    let pixel = vec2<u32>(invocation_id.xy);
+    let size = vec2<u32>(textureDimensions(texture));
+    if (pixel.x >= size.x || pixel.y >= size.y) {
         return ;
     }
 
-    var color = AIR_COLOR;
+    cell = textureLoad(texture, vec2<i32>(pixel));
+    for (var i = 0u; i < arrayLength(&edits); i++) {
+        shape = edits[i];
+        draw_shape(pixel);
+    }
+    if (painted) {
+        textureStore(texture, vec2<i32>(pixel), cell);
+    }
+}
+
+fn draw_shape(pixel: vec2<u32>) {
+    let color = element_color(material_element(pixel));
+
+    let pos = vec2<f32>(pixel);
+    if (shape.shape == SHAPE_RECTANGLE) {
+        draw_rectangle(pos, color);
+    } else if (shape.shape == SHAPE_ELLIPSE) {
+        draw_ellipse(pos, color);
+    } else if (shape.draw_radius > 0.0) {
+        // Draw circle
+        let point_on_line = closest_point_on_line(shape.draw_start, shape.draw_end, pos);
+        draw_particle_circle(pos, point_on_line, shape.draw_radius, color);
+    }
+}
+
+// Axis aligned rectangle with draw_start and draw_end as opposite corners,
+// hollow rectangles keep an outline draw_radius pixels wide
+fn draw_rectangle(pos: vec2<f32>, color: vec4<f32>) {
+    let min_corner = round(min(shape.draw_start, shape.draw_end));
+    let max_corner = round(max(shape.draw_start, shape.draw_end));
+    if (any(pos < min_corner) || any(pos > max_corner)) {
+        return;
+    }
+    if (shape.hollow != 0u) {
+        let width = max(shape.draw_radius, 1.0);
+        let inner_min = min_corner + vec2<f32>(width);
+        let inner_max = max_corner - vec2<f32>(width);
+        if (all(pos >= inner_min) && all(pos <= inner_max)) {
+            return;
+        }
+    }
+    paint(pos, color);
+}
+
+// Ellipse inscribed in the rectangle spanned by draw_start and draw_end
+fn draw_ellipse(pos: vec2<f32>, color: vec4<f32>) {
+    let center = (shape.draw_start + shape.draw_end) * 0.5;
+    let radii = max(abs(shape.draw_end - shape.draw_start) * 0.5, vec2<f32>(0.5));
+    if (!inside_ellipse(pos - center, radii)) {
+        return;
+    }
+    if (shape.hollow != 0u) {
+        let inner_radii = radii - vec2<f32>(max(shape.draw_radius, 1.0));
+        if (all(inner_radii > vec2<f32>(0.0)) && inside_ellipse(pos - center, inner_radii)) {
+            return;
+        }
+    }
+    paint(pos, color);
+}
+
+fn inside_ellipse(offset: vec2<f32>, radii: vec2<f32>) -> bool {
+    let normalized = offset / radii;
+    return dot(normalized, normalized) <= 1.0;
+}
+
+fn element_color(element: u32) -> vec4<f32> {
+    if(element == 0u){
+        return AIR_COLOR;
+    }else if(element == 1u){
+        return SAND_COLOR;
+    }else if(element == 2u){
+        return WATER_COLOR;
+    }
+    return ROCK_COLOR;
+}
+
+fn compare_vectors(v1: vec4<f32>, v2: vec4<f32>) -> bool {
+    return distance(v1, v2) < 0.01;
+}
+
+fn color_element(color: vec4<f32>) -> u32 {
+    if(compare_vectors(color, AIR_COLOR)){
+        return 0u;
+    }else if(compare_vectors(color, SAND_COLOR)){
+        return 1u;
+    }else if(compare_vectors(color, WATER_COLOR)){
+        return 2u;
+    }else if(compare_vectors(color, ROCK_COLOR)){
+        return 3u;
+    }
+    return NO_ELEMENT;
+}
+
+// Paints the cell color unless the brush mode leaves the element currently there alone
+fn paint(pos: vec2<f32>, color: vec4<f32>) {
+    if (shape.mode != MODE_OVERWRITE) {
+        let current = color_element(cell);
+        if (shape.mode == MODE_ONLY_EMPTY && current != 0u) {
+            return;
+        }
+        if (shape.mode == MODE_REPLACE_ONLY && current != shape.mode_element) {
+            return;
+        }
+        if (shape.mode == MODE_PROTECT && current == shape.mode_element) {
+            return;
+        }
+    }
+    cell = color;
+    painted = true;
+}
+
+fn hash(value: u32) -> u32 {
+    var state = value;
+    state = state ^ 2747636419u;
+    state = state * 2654435769u;
+    state = state ^ state >> 16u;
+    state = state * 2654435769u;
+    state = state ^ state >> 16u;
+    state = state * 2654435769u;
+    return state;
+}
+
+fn packed_byte(packed: u32, index: u32) -> u32 {
+    return (packed >> (index * 8u)) & 255u;
+}
 
-    if(pc.element == 0u){
-        color = AIR_COLOR;
-    }else if(pc.element == 1u){
-        color = SAND_COLOR;
-    }else if(pc.element == 2u){
-        color = WATER_COLOR;
-    }else{
-        color = ROCK_COLOR;
+// Element painted at this pixel, depending on the brush material
+fn material_element(pixel: vec2<u32>) -> u32 {
+    if (shape.material_count == 0u || shape.material == MATERIAL_SINGLE) {
+        return shape.element;
+    }
+
+    if (shape.material == MATERIAL_MIX) {
+        let roll = hash(hash(pixel.y * 65536u + pixel.x) ^ shape.seed) % 255u;
+        for (var i = 0u; i < shape.material_count; i++) {
+            if (roll < packed_byte(shape.material_weights, i)) {
+                return packed_byte(shape.material_elements, i);
+            }
+        }
+        return packed_byte(shape.material_elements, shape.material_count - 1u);
     }
 
-    // Draw circle
-    if (pc.draw_radius > 0.0) {
-        let pos = vec2<f32>(pixel);
-        let point_on_line = closest_point_on_line(pc.draw_start, pc.draw_end, pos);
-        draw_particle_circle(pos, point_on_line, pc.draw_radius, color);
+    let tile = pixel / max(shape.material_scale, 1u);
+    var index = tile.y;
+    if (shape.material == MATERIAL_CHECKER) {
+        index = tile.x + tile.y;
     }
+    return packed_byte(shape.material_elements, index % shape.material_count);
 }
 
 // Line v->w, point p
@@ -66,7 +252,7 @@ fn draw_particle_circle(pos: vec2<f32>, draw_pos: vec2<f32>, radius: f32, color:
         let diff = pos - draw_pos;
         let dist = length(diff);
         if (round(dist) <= radius) {
-            textureStore(texture, vec2<i32>(pos), color);
+            paint(pos, color);
         }
     }
 }
\ No newline at end of file
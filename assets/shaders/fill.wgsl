@@ -0,0 +1,106 @@
+const AIR_COLOR = vec4<f32>(0.02, 0.02, 0.02, 1.0);
+const SAND_COLOR = vec4<f32>(0.8, 0.8, 0.2, 1.0);
+const WATER_COLOR = vec4<f32>(0.2, 0.2, 0.8, 1.0);
+const ROCK_COLOR = vec4<f32>(0.4, 0.4, 0.4, 1.0);
+
+struct PushConstants {
+    seed: vec2<u32>,
+    element: u32,
+    budget: u32,
+}
+var<push_constant> pc: PushConstants;
+
+struct FillState {
+    target_color: vec4<f32>,
+    filled: atomic<u32>,
+}
+
+@group(0) @binding(0)
+var texture: texture_storage_2d<rgba8unorm, read_write>;
+
+// 1 for every cell that is part of the fill, cleared before each new fill
+@group(0) @binding(1)
+var<storage, read_write> mask: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read_write> state: FillState;
+
+// the world as it was when the fill started, the region grows over this while the cells keep moving
+@group(0) @binding(3)
+var snapshot: texture_2d<f32>;
+
+fn element_color(element: u32) -> vec4<f32> {
+    if(element == 0u){
+        return AIR_COLOR;
+    }else if(element == 1u){
+        return SAND_COLOR;
+    }else if(element == 2u){
+        return WATER_COLOR;
+    }
+    return ROCK_COLOR;
+}
+
+fn compare_vectors(v1: vec4<f32>, v2: vec4<f32>) -> bool {
+    return distance(v1, v2) < 0.01;
+}
+
+// Starts a fill at the seed cell, remembering which element the region is made of
+@compute @workgroup_size(1, 1, 1)
+fn seed() {
+    let size = vec2<u32>(textureDimensions(texture));
+    let color = element_color(pc.element);
+    if (pc.seed.x >= size.x || pc.seed.y >= size.y) {
+        atomicStore(&state.filled, pc.budget);
+        return;
+    }
+
+    let target_color = textureLoad(snapshot, vec2<i32>(pc.seed), 0);
+    state.target_color = target_color;
+    // the region already is the selected element, nothing to do
+    if (compare_vectors(target_color, color)) {
+        atomicStore(&state.filled, pc.budget);
+        return;
+    }
+
+    mask[pc.seed.y * size.x + pc.seed.x] = 1u;
+    atomicStore(&state.filled, 1u);
+    textureStore(texture, vec2<i32>(pc.seed), color);
+}
+
+// Grows the fill by (at least) one cell in every direction
+@compute @workgroup_size(8, 8, 1)
+fn spread(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let pixel = invocation_id.xy;
+    let size = vec2<u32>(textureDimensions(texture));
+    if (pixel.x >= size.x || pixel.y >= size.y) {
+        return;
+    }
+
+    let index = pixel.y * size.x + pixel.x;
+    if (mask[index] != 0u) {
+        return;
+    }
+    if (!compare_vectors(textureLoad(snapshot, vec2<i32>(pixel), 0), state.target_color)) {
+        return;
+    }
+
+    var touches_fill = false;
+    if (pixel.x > 0u && mask[index - 1u] != 0u) {
+        touches_fill = true;
+    } else if (pixel.x + 1u < size.x && mask[index + 1u] != 0u) {
+        touches_fill = true;
+    } else if (pixel.y > 0u && mask[index - size.x] != 0u) {
+        touches_fill = true;
+    } else if (pixel.y + 1u < size.y && mask[index + size.x] != 0u) {
+        touches_fill = true;
+    }
+    if (!touches_fill) {
+        return;
+    }
+
+    if (atomicAdd(&state.filled, 1u) >= pc.budget) {
+        return;
+    }
+    mask[index] = 1u;
+    textureStore(texture, vec2<i32>(pixel), element_color(pc.element));
+}
@@ -3,6 +3,16 @@ const SAND_COLOR = vec4<f32>(0.8, 0.8, 0.2, 1.0);
 const WATER_COLOR = vec4<f32>(0.2, 0.2, 0.8, 1.0);
 const ROCK_COLOR = vec4<f32>(0.4, 0.4, 0.4, 1.0);
 
+// rule flags, keep in sync with worlds.rs
+const RULE_SAND_SLIDES: u32 = 1u;
+const RULE_WATER_SPREADS: u32 = 2u;
+
+struct PushConstants {
+    // RULE_* flags the world steps by
+    rules: u32,
+}
+var<push_constant> pc: PushConstants;
+
 @group(0) @binding(0)
 var texture: texture_storage_2d<rgba8unorm, read_write>;
 
@@ -58,7 +68,7 @@ fn update(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
         if(compare_vectors(color_below, AIR_COLOR)){
             textureStore(texture, location, AIR_COLOR);
             textureStore(texture, location + vec2<i32>(0, 1), SAND_COLOR);
-        }else if(compare_vectors(color_below, SAND_COLOR)){
+        }else if(compare_vectors(color_below, SAND_COLOR) && (pc.rules & RULE_SAND_SLIDES) != 0u){
             if(compare_vectors(color_diagonally_below, AIR_COLOR)){
                 textureStore(texture, location, AIR_COLOR);
                 textureStore(texture, location + vec2<i32>(x, 1), SAND_COLOR);
@@ -79,6 +89,8 @@ fn update(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
         if(compare_vectors(color_below, AIR_COLOR)){
             textureStore(texture, location, AIR_COLOR);
             textureStore(texture, location + vec2<i32>(0, 1), WATER_COLOR);
+        }else if((pc.rules & RULE_WATER_SPREADS) == 0u){
+
         }else if(compare_vectors(color_below, WATER_COLOR)){
             // if(compare_vectors(color_below, WATER_COLOR)){
                 if(compare_vectors(color_diagonally_below, AIR_COLOR)){
@@ -0,0 +1,53 @@
+const AIR_COLOR = vec4<f32>(0.02, 0.02, 0.02, 1.0);
+const SAND_COLOR = vec4<f32>(0.8, 0.8, 0.2, 1.0);
+const WATER_COLOR = vec4<f32>(0.2, 0.2, 0.8, 1.0);
+const ROCK_COLOR = vec4<f32>(0.4, 0.4, 0.4, 1.0);
+
+// one counter per element index, the last one for colors that match no element
+const COUNTERS = 5u;
+
+@group(0) @binding(0)
+var texture: texture_storage_2d<rgba8unorm, read_write>;
+
+// cleared before every count
+@group(0) @binding(1)
+var<storage, read_write> counts: array<atomic<u32>, 5>;
+
+var<workgroup> local_counts: array<atomic<u32>, 5>;
+
+fn compare_vectors(v1: vec4<f32>, v2: vec4<f32>) -> bool {
+    return distance(v1, v2) < 0.01;
+}
+
+fn color_counter(color: vec4<f32>) -> u32 {
+    if(compare_vectors(color, AIR_COLOR)){
+        return 0u;
+    }else if(compare_vectors(color, SAND_COLOR)){
+        return 1u;
+    }else if(compare_vectors(color, WATER_COLOR)){
+        return 2u;
+    }else if(compare_vectors(color, ROCK_COLOR)){
+        return 3u;
+    }
+    return 4u;
+}
+
+// Counts the cells of every element, summed per workgroup first to keep the global atomics quiet
+@compute @workgroup_size(8, 8, 1)
+fn count(@builtin(global_invocation_id) invocation_id: vec3<u32>, @builtin(local_invocation_index) local_index: u32) {
+    if (local_index < COUNTERS) {
+        atomicStore(&local_counts[local_index], 0u);
+    }
+    workgroupBarrier();
+
+    let size = vec2<u32>(textureDimensions(texture));
+    if (invocation_id.x < size.x && invocation_id.y < size.y) {
+        let counter = color_counter(textureLoad(texture, vec2<i32>(invocation_id.xy)));
+        atomicAdd(&local_counts[counter], 1u);
+    }
+    workgroupBarrier();
+
+    if (local_index < COUNTERS) {
+        atomicAdd(&counts[local_index], atomicLoad(&local_counts[local_index]));
+    }
+}
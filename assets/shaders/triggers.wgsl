@@ -0,0 +1,88 @@
+const AIR_COLOR = vec4<f32>(0.02, 0.02, 0.02, 1.0);
+const SAND_COLOR = vec4<f32>(0.8, 0.8, 0.2, 1.0);
+const WATER_COLOR = vec4<f32>(0.2, 0.2, 0.8, 1.0);
+const ROCK_COLOR = vec4<f32>(0.4, 0.4, 0.4, 1.0);
+
+const ELEMENTS = 4u;
+const NO_ELEMENT = 255u;
+
+struct PushConstants {
+    region: u32,
+    generation: u32,
+}
+var<push_constant> pc: PushConstants;
+
+// cells from min up to max, already clipped to the world
+struct TriggerRegion {
+    min: vec2<u32>,
+    max: vec2<u32>,
+    // start of the region's cells in masks, one u32 per cell row by row
+    mask_offset: u32,
+    has_mask: u32,
+}
+
+@group(0) @binding(0)
+var texture: texture_storage_2d<rgba8unorm, read_write>;
+
+@group(0) @binding(1)
+var<storage, read> regions: array<TriggerRegion>;
+
+@group(0) @binding(2)
+var<storage, read> masks: array<u32>;
+
+// the generation of the regions counted, then one counter per element for every region, cleared before every count
+@group(0) @binding(3)
+var<storage, read_write> counts: array<atomic<u32>>;
+
+var<workgroup> local_counts: array<atomic<u32>, 4>;
+
+fn compare_vectors(v1: vec4<f32>, v2: vec4<f32>) -> bool {
+    return distance(v1, v2) < 0.01;
+}
+
+fn color_element(color: vec4<f32>) -> u32 {
+    if(compare_vectors(color, AIR_COLOR)){
+        return 0u;
+    }else if(compare_vectors(color, SAND_COLOR)){
+        return 1u;
+    }else if(compare_vectors(color, WATER_COLOR)){
+        return 2u;
+    }else if(compare_vectors(color, ROCK_COLOR)){
+        return 3u;
+    }
+    return NO_ELEMENT;
+}
+
+// Tags the counts with the regions they were made for, so the CPU can drop counts of stale regions
+@compute @workgroup_size(1, 1, 1)
+fn header() {
+    atomicStore(&counts[0], pc.generation);
+}
+
+// Counts the cells of every element inside one region, dispatched over the region's bounding box
+@compute @workgroup_size(8, 8, 1)
+fn count(@builtin(global_invocation_id) invocation_id: vec3<u32>, @builtin(local_invocation_index) local_index: u32) {
+    if (local_index < ELEMENTS) {
+        atomicStore(&local_counts[local_index], 0u);
+    }
+    workgroupBarrier();
+
+    let region = regions[pc.region];
+    let cell = region.min + invocation_id.xy;
+    var counted = all(cell < region.max);
+    if (counted && region.has_mask != 0u) {
+        let width = region.max.x - region.min.x;
+        counted = masks[region.mask_offset + invocation_id.y * width + invocation_id.x] != 0u;
+    }
+    if (counted) {
+        let element = color_element(textureLoad(texture, vec2<i32>(cell)));
+        if (element != NO_ELEMENT) {
+            atomicAdd(&local_counts[element], 1u);
+        }
+    }
+    workgroupBarrier();
+
+    if (local_index < ELEMENTS) {
+        atomicAdd(&counts[1u + pc.region * ELEMENTS + local_index], atomicLoad(&local_counts[local_index]));
+    }
+}
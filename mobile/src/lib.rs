@@ -14,7 +14,7 @@ fn main() {
                 }),
                 ..default()
             }),
-            PixelWorldPlugin,
+            PixelWorldPlugin::default(),
         ))
         .run()
 }
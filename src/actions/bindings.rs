@@ -0,0 +1,293 @@
+use bevy::prelude::*;
+use bevy::reflect::{DynamicEnum, DynamicVariant};
+
+use crate::actions::Action;
+use crate::tools::DrawTool;
+
+/// Written next to the `assets` folder, one `Action = Binding, Binding` line per action.
+#[cfg(not(target_arch = "wasm32"))]
+const BINDINGS_FILE: &str = "pixel_world_bindings.cfg";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InputKind {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// Matched on every connected gamepad
+    Gamepad(GamepadButtonType),
+    WheelUp,
+    WheelDown,
+}
+
+/// An input together with the modifiers that must be held for it to trigger its action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Binding {
+    pub input: InputKind,
+    pub ctrl: bool,
+    pub shift: bool,
+}
+
+impl Binding {
+    pub fn new(input: InputKind) -> Self {
+        Self {
+            input,
+            ctrl: false,
+            shift: false,
+        }
+    }
+
+    pub fn key(key: KeyCode) -> Self {
+        Self::new(InputKind::Key(key))
+    }
+
+    pub fn mouse(button: MouseButton) -> Self {
+        Self::new(InputKind::Mouse(button))
+    }
+
+    pub fn gamepad(button: GamepadButtonType) -> Self {
+        Self::new(InputKind::Gamepad(button))
+    }
+
+    pub fn with_ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+
+    pub fn with_shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    /// Short human readable form, e.g. `Ctrl+C` or `Pad South`.
+    pub fn label(&self) -> String {
+        let input = match self.input {
+            InputKind::Key(key) => format!("{key:?}"),
+            InputKind::Mouse(button) => format!("{button:?} Mouse"),
+            InputKind::Gamepad(button) => format!("Pad {button:?}"),
+            InputKind::WheelUp => "Wheel Up".to_string(),
+            InputKind::WheelDown => "Wheel Down".to_string(),
+        };
+        self.with_modifiers(input)
+    }
+
+    /// Form used in the config file, e.g. `Ctrl+Key(C)`, read back by [`Binding::parse`].
+    fn to_config(self) -> String {
+        let input = match self.input {
+            InputKind::Key(key) => format!("Key({key:?})"),
+            InputKind::Mouse(button) => format!("Mouse({button:?})"),
+            InputKind::Gamepad(button) => format!("Gamepad({button:?})"),
+            InputKind::WheelUp => "WheelUp".to_string(),
+            InputKind::WheelDown => "WheelDown".to_string(),
+        };
+        self.with_modifiers(input)
+    }
+
+    fn with_modifiers(&self, input: String) -> String {
+        match (self.ctrl, self.shift) {
+            (true, true) => format!("Ctrl+Shift+{input}"),
+            (true, false) => format!("Ctrl+{input}"),
+            (false, true) => format!("Shift+{input}"),
+            (false, false) => input,
+        }
+    }
+
+    fn parse(text: &str) -> Option<Binding> {
+        let mut binding = Binding::new(InputKind::WheelUp);
+        let mut rest = text.trim();
+        loop {
+            if let Some(stripped) = rest.strip_prefix("Ctrl+") {
+                binding.ctrl = true;
+                rest = stripped;
+            } else if let Some(stripped) = rest.strip_prefix("Shift+") {
+                binding.shift = true;
+                rest = stripped;
+            } else {
+                break;
+            }
+        }
+
+        binding.input = match rest {
+            "WheelUp" => InputKind::WheelUp,
+            "WheelDown" => InputKind::WheelDown,
+            _ => {
+                let (kind, name) = rest.strip_suffix(')')?.split_once('(')?;
+                // buttons without a name of their own are written as `Other(n)`
+                let other = name.strip_prefix("Other(").and_then(|index| index.strip_suffix(')'));
+                match (kind, other) {
+                    ("Key", _) => InputKind::Key(unit_variant(name)?),
+                    ("Mouse", Some(index)) => InputKind::Mouse(MouseButton::Other(index.parse().ok()?)),
+                    ("Mouse", None) => InputKind::Mouse(unit_variant(name)?),
+                    ("Gamepad", Some(index)) => InputKind::Gamepad(GamepadButtonType::Other(index.parse().ok()?)),
+                    ("Gamepad", None) => InputKind::Gamepad(unit_variant(name)?),
+                    _ => return None,
+                }
+            }
+        };
+        Some(binding)
+    }
+}
+
+/// Unit variant of a reflected input enum by name, e.g. `KeyCode::Space` from `"Space"`.
+fn unit_variant<T: FromReflect>(name: &str) -> Option<T> {
+    T::from_reflect(&DynamicEnum::new(name, DynamicVariant::Unit))
+}
+
+/// Bindings of every [`Action`], in the order of [`Action::all`].
+#[derive(Resource, Clone, Debug)]
+pub struct ActionMap {
+    bindings: Vec<(Action, Vec<Binding>)>,
+}
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        Self {
+            bindings: Action::all()
+                .into_iter()
+                .map(|action| (action, default_bindings(action)))
+                .collect(),
+        }
+    }
+}
+
+impl ActionMap {
+    pub fn iter(&self) -> impl Iterator<Item = (Action, &Vec<Binding>)> {
+        self.bindings.iter().map(|(action, bindings)| (*action, bindings))
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map_or(&[], |(_, bindings)| bindings.as_slice())
+    }
+
+    pub fn set_bindings(&mut self, action: Action, bindings: Vec<Binding>) {
+        if let Some((_, current)) = self.bindings.iter_mut().find(|(a, _)| *a == action) {
+            *current = bindings;
+        }
+    }
+
+    /// Label of the first binding of `action`, for hints in the UI.
+    pub fn label(&self, action: Action) -> Option<String> {
+        self.bindings(action).first().map(Binding::label)
+    }
+
+    /// The saved bindings, falling back to the defaults for actions missing from the config file.
+    pub fn load() -> Self {
+        let mut map = Self::default();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let path = bevy::asset::io::file::FileAssetReader::get_base_path().join(BINDINGS_FILE);
+            if let Ok(config) = std::fs::read_to_string(&path) {
+                map.apply_config(&config);
+            }
+        }
+        map
+    }
+
+    pub fn save(&self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let path = bevy::asset::io::file::FileAssetReader::get_base_path().join(BINDINGS_FILE);
+            if let Err(error) = std::fs::write(&path, self.to_config()) {
+                warn!("Failed to save bindings {error:?}");
+            }
+        }
+    }
+
+    fn to_config(&self) -> String {
+        self.bindings
+            .iter()
+            .map(|(action, bindings)| {
+                let bindings: Vec<String> = bindings.iter().map(|binding| binding.to_config()).collect();
+                format!("{} = {}\n", action.name(), bindings.join(", "))
+            })
+            .collect()
+    }
+
+    fn apply_config(&mut self, config: &str) {
+        let actions = Action::all();
+        for line in config.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let Some((name, bindings)) = line.split_once('=') else {
+                warn!("Ignoring binding line {line:?}");
+                continue;
+            };
+            let Some(action) = actions.iter().find(|action| action.name() == name.trim()) else {
+                warn!("Ignoring bindings of unknown action {:?}", name.trim());
+                continue;
+            };
+            let bindings = bindings
+                .split(',')
+                .filter(|binding| !binding.trim().is_empty())
+                .filter_map(|binding| {
+                    let parsed = Binding::parse(binding);
+                    if parsed.is_none() {
+                        warn!("Ignoring unknown binding {:?}", binding.trim());
+                    }
+                    parsed
+                })
+                .collect();
+            self.set_bindings(*action, bindings);
+        }
+    }
+}
+
+fn default_bindings(action: Action) -> Vec<Binding> {
+    use GamepadButtonType as Pad;
+
+    match action {
+        Action::Draw => vec![Binding::mouse(MouseButton::Left), Binding::gamepad(Pad::RightTrigger2)],
+        Action::Erase => vec![Binding::mouse(MouseButton::Right), Binding::gamepad(Pad::LeftTrigger2)],
+        Action::SelectElement(element) => vec![Binding::key(element.hotkey())],
+        // the bumpers
+        Action::NextElement => vec![Binding::gamepad(Pad::RightTrigger)],
+        Action::PreviousElement => vec![Binding::gamepad(Pad::LeftTrigger)],
+        Action::FilterElement(element) => vec![Binding::key(element.hotkey()).with_shift()],
+        Action::PanUp => vec![Binding::key(KeyCode::W), Binding::key(KeyCode::Up), Binding::gamepad(Pad::DPadUp)],
+        Action::PanDown => vec![Binding::key(KeyCode::S), Binding::key(KeyCode::Down), Binding::gamepad(Pad::DPadDown)],
+        Action::PanLeft => vec![Binding::key(KeyCode::A), Binding::key(KeyCode::Left), Binding::gamepad(Pad::DPadLeft)],
+        Action::PanRight => vec![Binding::key(KeyCode::D), Binding::key(KeyCode::Right), Binding::gamepad(Pad::DPadRight)],
+        Action::ZoomIn => vec![Binding::new(InputKind::WheelUp), Binding::key(KeyCode::Equals)],
+        Action::ZoomOut => vec![Binding::new(InputKind::WheelDown), Binding::key(KeyCode::Minus)],
+        Action::DragPan => vec![Binding::mouse(MouseButton::Middle)],
+        Action::FitWorld => vec![Binding::key(KeyCode::Home), Binding::gamepad(Pad::North)],
+        Action::ToggleIntegerScale => vec![Binding::key(KeyCode::P)],
+        Action::Pause => vec![Binding::key(KeyCode::Space), Binding::gamepad(Pad::Start)],
+        Action::Step => vec![Binding::key(KeyCode::Period), Binding::gamepad(Pad::Select)],
+        Action::BrushSizeUp => vec![Binding::key(KeyCode::BracketRight)],
+        Action::BrushSizeDown => vec![Binding::key(KeyCode::BracketLeft)],
+        Action::SelectTool(tool) => {
+            let key = match tool {
+                DrawTool::Brush => KeyCode::Key1,
+                DrawTool::Line => KeyCode::Key2,
+                DrawTool::Rectangle => KeyCode::Key3,
+                DrawTool::Ellipse => KeyCode::Key4,
+                DrawTool::Fill => KeyCode::Key5,
+                DrawTool::Select => KeyCode::Key6,
+                // only entered through Ctrl+V or the stamp panel
+                DrawTool::Paste => return Vec::new(),
+            };
+            vec![Binding::key(key)]
+        }
+        Action::ToggleHollow => vec![Binding::key(KeyCode::H)],
+        Action::CycleMaterial => vec![Binding::key(KeyCode::M)],
+        Action::CycleBrushMode => vec![Binding::key(KeyCode::V)],
+        Action::Copy => vec![Binding::key(KeyCode::C).with_ctrl()],
+        Action::Cut => vec![Binding::key(KeyCode::X).with_ctrl()],
+        Action::Paste => vec![Binding::key(KeyCode::V).with_ctrl()],
+        Action::RotateClipboard => vec![Binding::key(KeyCode::Q)],
+        Action::FlipClipboard => vec![Binding::key(KeyCode::F)],
+        Action::Cancel => vec![Binding::key(KeyCode::Escape)],
+        Action::ToggleStampPanel => vec![Binding::key(KeyCode::Tab)],
+        Action::SaveStamp => vec![Binding::key(KeyCode::B).with_ctrl()],
+        Action::ToggleInspector => vec![Binding::key(KeyCode::I)],
+        Action::ToggleStats => vec![Binding::key(KeyCode::F3)],
+        Action::CycleDebugView => vec![Binding::key(KeyCode::F4)],
+        Action::Screenshot => vec![Binding::key(KeyCode::F12)],
+        Action::ToggleRecording => vec![Binding::key(KeyCode::F9)],
+        Action::ToggleGifRecording => vec![Binding::key(KeyCode::F9).with_shift()],
+        Action::SaveScene => vec![Binding::key(KeyCode::S).with_ctrl()],
+        Action::LoadScene => vec![Binding::key(KeyCode::O).with_ctrl()],
+        Action::ToggleBindingsPanel => vec![Binding::key(KeyCode::F1)],
+    }
+}
+
@@ -1,60 +1,247 @@
-use bevy::math::Vec3Swizzles;
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::input::InputSystem;
 use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
 
-use crate::actions::game_control::{get_movement, GameControl};
-use crate::player::Player;
-use crate::GameState;
+use crate::tools::DrawTool;
+use crate::CurrentElement;
 
-mod game_control;
+pub use bindings::{ActionMap, Binding, InputKind};
+pub use rebind::RebindPlugin;
 
-pub const FOLLOW_EPSILON: f32 = 5.;
+/// Wheel distance of one notch for wheels that report pixels.
+const PIXELS_PER_LINE: f32 = 38.0;
+
+mod bindings;
+mod rebind;
 
 pub struct ActionsPlugin;
 
-// This plugin listens for keyboard input and converts the input into Actions
-// Actions can then be used as a resource in other systems to act on the player input.
+// This plugin maps keyboard, mouse and gamepad input onto Actions through a rebindable ActionMap.
+// The resulting ActionState resource is what the rest of the sandbox reads its controls from.
 impl Plugin for ActionsPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<Actions>().add_systems(
-            Update,
-            set_movement_actions.run_if(in_state(GameState::Playing)),
-        );
+        app.insert_resource(ActionMap::load())
+            .init_resource::<ActionState>()
+            .add_systems(PreUpdate, update_action_state.after(InputSystem));
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Draw,
+    Erase,
+    SelectElement(CurrentElement),
+    NextElement,
+    PreviousElement,
+    /// Picks the element the filtering brush modes apply to
+    FilterElement(CurrentElement),
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    ZoomIn,
+    ZoomOut,
+    /// Pans the camera along with the mouse while held
+    DragPan,
+    /// Zooms and centers the camera so the whole world is visible
+    FitWorld,
+    /// Toggles zooming in whole screen pixels per cell only
+    ToggleIntegerScale,
+    Pause,
+    /// Advances a paused simulation by a single tick
+    Step,
+    BrushSizeUp,
+    BrushSizeDown,
+    SelectTool(DrawTool),
+    ToggleHollow,
+    CycleMaterial,
+    CycleBrushMode,
+    Copy,
+    Cut,
+    Paste,
+    RotateClipboard,
+    FlipClipboard,
+    Cancel,
+    ToggleStampPanel,
+    SaveStamp,
+    ToggleInspector,
+    ToggleStats,
+    CycleDebugView,
+    Screenshot,
+    ToggleRecording,
+    ToggleGifRecording,
+    SaveScene,
+    LoadScene,
+    ToggleBindingsPanel,
+}
+
+impl Action {
+    /// Every action, in the order they are listed in the bindings panel and config file.
+    pub fn all() -> Vec<Action> {
+        let mut actions = vec![Action::Draw, Action::Erase];
+        actions.extend(CurrentElement::ALL.map(Action::SelectElement));
+        actions.extend([Action::NextElement, Action::PreviousElement]);
+        actions.extend(CurrentElement::ALL.map(Action::FilterElement));
+        actions.extend([
+            Action::PanUp,
+            Action::PanDown,
+            Action::PanLeft,
+            Action::PanRight,
+            Action::ZoomIn,
+            Action::ZoomOut,
+            Action::DragPan,
+            Action::FitWorld,
+            Action::ToggleIntegerScale,
+            Action::Pause,
+            Action::Step,
+            Action::BrushSizeUp,
+            Action::BrushSizeDown,
+        ]);
+        actions.extend(DrawTool::SELECTABLE.map(Action::SelectTool));
+        actions.extend([
+            Action::ToggleHollow,
+            Action::CycleMaterial,
+            Action::CycleBrushMode,
+            Action::Copy,
+            Action::Cut,
+            Action::Paste,
+            Action::RotateClipboard,
+            Action::FlipClipboard,
+            Action::Cancel,
+            Action::ToggleStampPanel,
+            Action::SaveStamp,
+            Action::ToggleInspector,
+            Action::ToggleStats,
+            Action::CycleDebugView,
+            Action::Screenshot,
+            Action::ToggleRecording,
+            Action::ToggleGifRecording,
+            Action::SaveScene,
+            Action::LoadScene,
+            Action::ToggleBindingsPanel,
+        ]);
+        actions
+    }
+
+    /// Name used in the config file and bindings panel.
+    pub fn name(&self) -> String {
+        format!("{self:?}")
     }
 }
 
+/// Which actions are held this frame, derived from the [`ActionMap`] every frame.
 #[derive(Default, Resource)]
-pub struct Actions {
-    pub player_movement: Option<Vec2>,
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    just_released: HashSet<Action>,
+    /// Wheel notches turned this frame, for actions pressed by the wheel
+    wheel_notches: HashMap<Action, u32>,
 }
 
-pub fn set_movement_actions(
-    mut actions: ResMut<Actions>,
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.just_released.contains(&action)
+    }
+
+    /// How many wheel notches pressed `action` this frame, 0 when the wheel did not press it.
+    pub fn wheel_notches(&self, action: Action) -> u32 {
+        self.wheel_notches.get(&action).copied().unwrap_or(0)
+    }
+}
+
+pub fn update_action_state(
+    map: Res<ActionMap>,
     keyboard_input: Res<Input<KeyCode>>,
-    touch_input: Res<Touches>,
-    player: Query<&Transform, With<Player>>,
-    camera: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    mut state: ResMut<ActionState>,
 ) {
-    let mut player_movement = Vec2::new(
-        get_movement(GameControl::Right, &keyboard_input)
-            - get_movement(GameControl::Left, &keyboard_input),
-        get_movement(GameControl::Up, &keyboard_input)
-            - get_movement(GameControl::Down, &keyboard_input),
-    );
-
-    if let Some(touch_position) = touch_input.first_pressed_position() {
-        let (camera, camera_transform) = camera.single();
-        if let Some(touch_position) = camera.viewport_to_world_2d(camera_transform, touch_position)
-        {
-            let diff = touch_position - player.single().translation.xy();
-            if diff.length() > FOLLOW_EPSILON {
-                player_movement = diff.normalize();
+    let wheel: f32 = mouse_wheel_events
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        })
+        .sum();
+    let ctrl = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    // (pressed, just pressed) of a single physical input
+    let input_state = |input: &InputKind| match input {
+        InputKind::Key(key) => (keyboard_input.pressed(*key), keyboard_input.just_pressed(*key)),
+        InputKind::Mouse(button) => (mouse_input.pressed(*button), mouse_input.just_pressed(*button)),
+        InputKind::Gamepad(button_type) => gamepads.iter().fold((false, false), |(pressed, just_pressed), gamepad| {
+            let button = GamepadButton::new(gamepad, *button_type);
+            (
+                pressed || gamepad_input.pressed(button),
+                just_pressed || gamepad_input.just_pressed(button),
+            )
+        }),
+        // the wheel has no held state, every frame it moves counts as a fresh press
+        InputKind::WheelUp => (wheel > 0.0, wheel > 0.0),
+        InputKind::WheelDown => (wheel < 0.0, wheel < 0.0),
+    };
+
+    let mut matches: Vec<(Action, &Binding, bool)> = Vec::new();
+    for (action, bindings) in map.iter() {
+        for binding in bindings {
+            if (binding.ctrl && !ctrl) || (binding.shift && !shift) {
+                continue;
+            }
+            let (pressed, just_pressed) = input_state(&binding.input);
+            if pressed {
+                matches.push((action, binding, just_pressed));
             }
         }
     }
 
-    if player_movement != Vec2::ZERO {
-        actions.player_movement = Some(player_movement.normalize());
-    } else {
-        actions.player_movement = None;
-    }
+    // when Ctrl+V and V both match, only the binding with more modifiers fires
+    let specificity = |binding: &Binding| binding.ctrl as u8 + binding.shift as u8;
+    let most_specific = |input: &InputKind| {
+        matches
+            .iter()
+            .filter(|(_, binding, _)| binding.input == *input)
+            .map(|(_, binding, _)| specificity(binding))
+            .max()
+            .unwrap_or(0)
+    };
+    let matches: Vec<(Action, &Binding, bool)> = matches
+        .iter()
+        .filter(|(_, binding, _)| specificity(binding) == most_specific(&binding.input))
+        .copied()
+        .collect();
+    // a small pixel scroll still counts as one notch
+    let notches = wheel.abs().round().max(1.0) as u32;
+    let wheel_notches = matches
+        .iter()
+        .filter(|(_, binding, _)| matches!(binding.input, InputKind::WheelUp | InputKind::WheelDown))
+        .map(|(action, _, _)| (*action, notches))
+        .collect();
+
+    let pressed: HashSet<Action> = matches.iter().map(|(action, _, _)| *action).collect();
+    let just_pressed = matches
+        .iter()
+        .filter(|(_, _, just_pressed)| *just_pressed)
+        .map(|(action, _, _)| *action)
+        .collect();
+    let just_released = state.pressed.difference(&pressed).copied().collect();
+
+    *state = ActionState {
+        pressed,
+        just_pressed,
+        just_released,
+        wheel_notches,
+    };
 }
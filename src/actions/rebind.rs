@@ -0,0 +1,198 @@
+use bevy::prelude::*;
+
+use crate::actions::{update_action_state, Action, ActionMap, ActionState, Binding, InputKind};
+
+const PANEL_COLOR: Color = Color::rgba(0.1, 0.1, 0.1, 0.85);
+const ROW_NORMAL: Color = Color::rgb(0.15, 0.15, 0.15);
+const ROW_HOVERED: Color = Color::rgb(0.25, 0.25, 0.25);
+const ROW_WAITING: Color = Color::rgb(0.35, 0.25, 0.1);
+const MODIFIER_KEYS: [KeyCode; 4] = [
+    KeyCode::ControlLeft,
+    KeyCode::ControlRight,
+    KeyCode::ShiftLeft,
+    KeyCode::ShiftRight,
+];
+
+/// Panel (toggled with F1) listing every action, clicking one waits for the next input and binds it,
+/// replacing the action's bindings of the same device (keyboard and mouse, or gamepad) and keeping the others.
+pub struct RebindPlugin;
+
+impl Plugin for RebindPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            .add_systems(Startup, spawn_bindings_panel)
+            .add_systems(PreUpdate, capture_binding.after(update_action_state))
+            .add_systems(Update, (toggle_bindings_panel, click_binding_row, update_binding_rows).chain());
+    }
+}
+
+/// Action waiting for its new binding.
+#[derive(Default, Resource)]
+struct Rebinding(Option<Action>);
+
+#[derive(Component)]
+struct BindingsPanel;
+
+#[derive(Component)]
+struct BindingRow(Action);
+
+fn spawn_bindings_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(10.),
+                    top: Val::Px(10.),
+                    max_height: Val::Percent(85.),
+                    flex_direction: FlexDirection::Column,
+                    flex_wrap: FlexWrap::Wrap,
+                    padding: UiRect::all(Val::Px(5.)),
+                    row_gap: Val::Px(2.),
+                    column_gap: Val::Px(5.),
+                    display: Display::None,
+                    ..default()
+                },
+                background_color: PANEL_COLOR.into(),
+                ..default()
+            },
+            // blocks drawing while the cursor is over the panel
+            Interaction::default(),
+            BindingsPanel,
+        ))
+        .with_children(|parent| {
+            for action in Action::all() {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(320.0),
+                                padding: UiRect::horizontal(Val::Px(4.)),
+                                ..default()
+                            },
+                            background_color: ROW_NORMAL.into(),
+                            ..default()
+                        },
+                        BindingRow(action),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            action.name(),
+                            TextStyle {
+                                font_size: 14.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+fn toggle_bindings_panel(
+    actions: Res<ActionState>,
+    mut rebinding: ResMut<Rebinding>,
+    mut panel: Query<&mut Style, With<BindingsPanel>>,
+) {
+    if !actions.just_pressed(Action::ToggleBindingsPanel) {
+        return;
+    }
+    for mut style in &mut panel {
+        style.display = match style.display {
+            Display::None => Display::Flex,
+            _ => Display::None,
+        };
+    }
+    rebinding.0 = None;
+}
+
+fn click_binding_row(
+    mut rebinding: ResMut<Rebinding>,
+    interaction_query: Query<(&Interaction, &BindingRow), Changed<Interaction>>,
+) {
+    for (interaction, row) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            rebinding.0 = Some(row.0);
+        }
+    }
+}
+
+// runs before the rest of the frame sees the input, so the captured press does not also trigger its old action
+fn capture_binding(
+    mut rebinding: ResMut<Rebinding>,
+    mut map: ResMut<ActionMap>,
+    mut actions: ResMut<ActionState>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepad_input: Res<Input<GamepadButton>>,
+) {
+    let Some(action) = rebinding.0 else { return };
+
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        rebinding.0 = None;
+        actions.just_pressed.clear();
+        return;
+    }
+
+    let input = keyboard_input
+        .get_just_pressed()
+        .find(|key| !MODIFIER_KEYS.contains(key))
+        .map(|key| InputKind::Key(*key))
+        .or_else(|| mouse_input.get_just_pressed().next().map(|button| InputKind::Mouse(*button)))
+        .or_else(|| {
+            gamepad_input
+                .get_just_pressed()
+                .next()
+                .map(|button| InputKind::Gamepad(button.button_type))
+        });
+    let Some(input) = input else { return };
+
+    let binding = Binding {
+        input,
+        ctrl: keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]),
+        shift: keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]),
+    };
+    let gamepad = |binding: &Binding| matches!(binding.input, InputKind::Gamepad(_));
+    let mut bindings: Vec<Binding> =
+        map.bindings(action).iter().filter(|kept| gamepad(kept) != gamepad(&binding)).copied().collect();
+    bindings.push(binding);
+    map.set_bindings(action, bindings);
+    map.save();
+    rebinding.0 = None;
+    actions.just_pressed.clear();
+}
+
+fn update_binding_rows(
+    map: Res<ActionMap>,
+    rebinding: Res<Rebinding>,
+    mut rows: Query<(&BindingRow, &Interaction, &mut BackgroundColor, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    let relabel = map.is_changed() || rebinding.is_changed();
+    for (row, interaction, mut color, children) in &mut rows {
+        let waiting = rebinding.0 == Some(row.0);
+        let background = match (waiting, interaction) {
+            (true, _) => ROW_WAITING,
+            (false, Interaction::None) => ROW_NORMAL,
+            (false, _) => ROW_HOVERED,
+        };
+        if color.0 != background {
+            color.0 = background;
+        }
+
+        if !relabel {
+            continue;
+        }
+        let label = if waiting {
+            "press a key, button or Escape".to_string()
+        } else {
+            let bindings: Vec<String> = map.bindings(row.0).iter().map(Binding::label).collect();
+            bindings.join(", ")
+        };
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = format!("{}: {label}", row.0.name());
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+// Runs a scene on the CPU without opening a window, for regression scenarios and pre-baking levels.
+
+use std::{path::PathBuf, process::ExitCode};
+
+use pixel_world::{
+    cpu,
+    scene::{load_scene, save_scene},
+    CurrentElement, PixelWorldRules,
+};
+
+const USAGE: &str = "Usage: pixel_world_cli <scene.png> [--ticks N] [--out final.png] [--frames DIR] [--every K]
+                      [--seed S] [--no-sand-slides] [--no-water-spreads]
+
+Loads a scene (a PNG of element colors, one pixel per cell) and runs N ticks (default 60) on the CPU.
+--out               writes the final state as a scene
+--frames            writes every Kth tick (default 1), starting with the loaded state, as DIR/frame_00000.png, ...
+--seed              shifts which way particles try to slide first (default 0)
+--no-sand-slides    sand resting on sand stays put instead of sliding diagonally
+--no-water-spreads  water that can not fall stays put instead of flowing sideways";
+
+struct Options {
+    scene: PathBuf,
+    ticks: u32,
+    out: Option<PathBuf>,
+    frames: Option<PathBuf>,
+    every: u32,
+    seed: u32,
+    rules: PixelWorldRules,
+}
+
+/// Parsed options, or `None` if the usage was asked for.
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut scene = None;
+    let mut options = Options {
+        scene: PathBuf::new(),
+        ticks: 60,
+        out: None,
+        frames: None,
+        every: 1,
+        seed: 0,
+        rules: PixelWorldRules::default(),
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{name} needs a value"));
+        match arg.as_str() {
+            "--ticks" => options.ticks = value("--ticks")?.parse().map_err(|_| "--ticks needs a number")?,
+            "--out" => options.out = Some(value("--out")?.into()),
+            "--frames" => options.frames = Some(value("--frames")?.into()),
+            "--every" => options.every = value("--every")?.parse().map_err(|_| "--every needs a number")?,
+            "--seed" => options.seed = value("--seed")?.parse().map_err(|_| "--seed needs a number")?,
+            "--no-sand-slides" => options.rules.sand_slides = false,
+            "--no-water-spreads" => options.rules.water_spreads = false,
+            "-h" | "--help" => return Ok(None),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ if scene.is_none() => scene = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument {arg}")),
+        }
+    }
+
+    options.scene = scene.ok_or("missing the scene to run")?;
+    options.every = options.every.max(1);
+    Ok(Some(options))
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let mut grid = load_scene(&options.scene).map_err(|error| format!("failed to load {}: {error}", options.scene.display()))?;
+
+    let write_frame = |grid: &_, frame: u32| -> Result<(), String> {
+        let Some(folder) = &options.frames else { return Ok(()) };
+        let path = folder.join(format!("frame_{frame:05}.png"));
+        save_scene(grid, &path).map_err(|error| format!("failed to write {}: {error}", path.display()))
+    };
+
+    let mut frames = 0;
+    write_frame(&grid, frames)?;
+    for tick in 1..=options.ticks {
+        cpu::step_with_rules(&mut grid, options.seed, options.rules);
+        if tick % options.every == 0 {
+            frames += 1;
+            write_frame(&grid, frames)?;
+        }
+    }
+
+    if let Some(out) = &options.out {
+        save_scene(&grid, out).map_err(|error| format!("failed to write {}: {error}", out.display()))?;
+    }
+
+    let counts: Vec<String> = CurrentElement::ALL
+        .into_iter()
+        .map(|element| format!("{} {}", element.name(), grid.count(element)))
+        .collect();
+    println!("{} ticks on {}x{} cells: {}", options.ticks, grid.size.x, grid.size.y, counts.join(", "));
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let result = parse_options(std::env::args().skip(1)).and_then(|options| match options {
+        Some(options) => run(options),
+        None => {
+            println!("{USAGE}");
+            Ok(())
+        }
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
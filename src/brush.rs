@@ -0,0 +1,139 @@
+use crate::CurrentElement;
+
+/// Most elements a mix or pattern can be made of, they are packed one byte each into a `u32`.
+pub const MAX_MATERIAL_ELEMENTS: usize = 4;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BrushPattern {
+    /// Square tiles alternating between the elements
+    Checker,
+    /// Horizontal bands of the elements
+    Stripes,
+}
+
+/// Which of the cells under the brush (and the shape tools) get painted.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum BrushMode {
+    /// Every cell is painted
+    #[default]
+    Overwrite,
+    /// Only AIR cells are painted
+    OnlyEmpty,
+    /// Only cells of the given element are painted
+    ReplaceOnly(CurrentElement),
+    /// Every cell except the ones of the given element is painted
+    Protect(CurrentElement),
+}
+
+impl BrushMode {
+    /// The mode following this one, filtering modes keep their element.
+    pub fn next(&self) -> BrushMode {
+        match self {
+            BrushMode::Overwrite => BrushMode::OnlyEmpty,
+            BrushMode::OnlyEmpty => BrushMode::ReplaceOnly(CurrentElement::ROCK),
+            BrushMode::ReplaceOnly(element) => BrushMode::Protect(*element),
+            BrushMode::Protect(_) => BrushMode::Overwrite,
+        }
+    }
+
+    /// Changes the element a filtering mode applies to, other modes are left untouched.
+    pub fn with_filter(&self, filter: CurrentElement) -> BrushMode {
+        match self {
+            BrushMode::ReplaceOnly(_) => BrushMode::ReplaceOnly(filter),
+            BrushMode::Protect(_) => BrushMode::Protect(filter),
+            mode => *mode,
+        }
+    }
+}
+
+/// What the brush (and the shape tools) paint with.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub enum BrushMaterial {
+    /// Only the selected element
+    #[default]
+    Single,
+    /// Random mix of elements, each picked with a probability proportional to its weight
+    Mix(Vec<(CurrentElement, u8)>),
+    /// Elements tiled in a fixed pattern aligned to the world, `scale` cells per tile
+    Pattern {
+        pattern: BrushPattern,
+        elements: Vec<CurrentElement>,
+        scale: u32,
+    },
+}
+
+impl BrushMaterial {
+    /// Materials cycled through with the material hotkey.
+    pub fn presets() -> Vec<BrushMaterial> {
+        vec![
+            BrushMaterial::Single,
+            BrushMaterial::Mix(vec![(CurrentElement::SAND, 70), (CurrentElement::ROCK, 30)]),
+            BrushMaterial::Pattern {
+                pattern: BrushPattern::Checker,
+                elements: vec![CurrentElement::SAND, CurrentElement::ROCK],
+                scale: 4,
+            },
+            BrushMaterial::Pattern {
+                pattern: BrushPattern::Stripes,
+                elements: vec![CurrentElement::SAND, CurrentElement::ROCK, CurrentElement::WATER],
+                scale: 3,
+            },
+        ]
+    }
+
+    /// The preset following this material, wrapping around.
+    pub fn next_preset(&self) -> BrushMaterial {
+        let presets = Self::presets();
+        let next = presets
+            .iter()
+            .position(|preset| preset == self)
+            .map_or(0, |index| (index + 1) % presets.len());
+        presets[next].clone()
+    }
+
+    /// Element indices packed one byte each, as read by `draw.wgsl`.
+    pub fn packed_elements(&self) -> u32 {
+        let elements: Vec<CurrentElement> = match self {
+            BrushMaterial::Single => Vec::new(),
+            BrushMaterial::Mix(mix) => mix.iter().map(|(element, _)| *element).collect(),
+            BrushMaterial::Pattern { elements, .. } => elements.clone(),
+        };
+        pack_bytes(elements.iter().map(|element| element.index()))
+    }
+
+    /// Cumulative mix weights scaled to `0..=255`, packed one byte each, as read by `draw.wgsl`.
+    pub fn packed_weights(&self) -> u32 {
+        let BrushMaterial::Mix(mix) = self else { return 0 };
+        let mix = &mix[..mix.len().min(MAX_MATERIAL_ELEMENTS)];
+        let total: u32 = mix.iter().map(|(_, weight)| *weight as u32).sum::<u32>().max(1);
+
+        let mut cumulative = 0;
+        pack_bytes(mix.iter().map(|(_, weight)| {
+            cumulative += *weight as u32;
+            cumulative * 255 / total
+        }))
+    }
+
+    pub fn element_count(&self) -> u32 {
+        let count = match self {
+            BrushMaterial::Single => 0,
+            BrushMaterial::Mix(mix) => mix.len(),
+            BrushMaterial::Pattern { elements, .. } => elements.len(),
+        };
+        count.min(MAX_MATERIAL_ELEMENTS) as u32
+    }
+
+    pub fn scale(&self) -> u32 {
+        match self {
+            BrushMaterial::Pattern { scale, .. } => (*scale).max(1),
+            _ => 1,
+        }
+    }
+}
+
+fn pack_bytes(values: impl Iterator<Item = u32>) -> u32 {
+    values
+        .take(MAX_MATERIAL_ELEMENTS)
+        .enumerate()
+        .fold(0, |packed, (i, value)| packed | (value.min(255) << (i * 8)))
+}
@@ -1,28 +1,131 @@
-use bevy::{app::{Plugin, Startup, Update}, time::Time, ecs::{system::{Res, Query}, event::EventReader, query::With}, input::{Input, keyboard::KeyCode, mouse::{MouseWheel, MouseScrollUnit}}, transform::components::Transform, render::camera::{OrthographicProjection, Camera}, math::Vec2};
+use bevy::{app::{Plugin, PostUpdate, Update}, time::Time, ecs::{system::{Local, Res, ResMut, Query, Resource}, query::{With, Without}, schedule::IntoSystemConfigs}, transform::{components::{GlobalTransform, Transform}, TransformSystem}, render::camera::{Camera, OrthographicProjection}, math::Vec2, window::{Window, PrimaryWindow}};
 
-const CAMERA_MOVE_SPEED: f32 = 500.0;
+use crate::{actions::{Action, ActionState}, gamepad::VirtualCursor, PixelWorld, PixelWorldCamera, PixelWorldConfig, PrimaryPixelWorld};
+
+/// World units per second at a zoom of 1, the speed grows with the zoom level so panning feels the same.
+pub const CAMERA_MOVE_SPEED: f32 = 500.0;
+/// Scale change of one zoom step, applied every frame a zoom action is held and for every wheel notch.
+const ZOOM_STEP: f32 = 1.05;
+pub const MIN_ZOOM: f32 = 0.15;
+pub const MAX_ZOOM: f32 = 5.;
 
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin{
     fn build(&self, app: &mut bevy::prelude::App) {
         app
-        .add_systems(Update, camera_controller);
+        .init_resource::<CameraSettings>()
+        .add_systems(Update, (camera_controller, drag_pan, fit_world_shortcut, toggle_integer_scale))
+        // after every system that moves the camera, before the move is seen by rendering
+        .add_systems(PostUpdate, (fit_on_resize, clamp_camera, snap_camera).chain().before(TransformSystem::TransformPropagate));
+    }
+}
+
+#[derive(Default, Resource)]
+pub struct CameraSettings {
+    /// Only zoom to whole numbers of screen pixels per cell (or cells per screen pixel), so cells never shimmer
+    pub integer_scale: bool,
+    /// Window size the camera was last fitted or checked against
+    last_window_size: Option<Vec2>,
+}
+
+/// Physical screen pixels per world cell at `scale`.
+fn pixel_ratio(scale: f32, scale_factor: f32) -> f32 {
+    scale_factor / scale
+}
+
+/// Smallest and largest integer ratios whose scale lies within [`MIN_ZOOM`] and [`MAX_ZOOM`].
+fn integer_ratio_range(scale_factor: f32) -> (f32, f32) {
+    let (min, max) = (pixel_ratio(MAX_ZOOM, scale_factor), pixel_ratio(MIN_ZOOM, scale_factor));
+    let min = if min >= 1.0 { min.ceil() } else { 1.0 / (1.0 / min).floor() };
+    let max = if max >= 1.0 { max.floor() } else { 1.0 / (1.0 / max).ceil() };
+    (min, max)
+}
+
+/// Closest ratio that is a whole number of pixels per cell, or of cells per pixel when zoomed out,
+/// within the zoom limits.
+fn snap_ratio(ratio: f32, scale_factor: f32) -> f32 {
+    let (min, max) = integer_ratio_range(scale_factor);
+    let ratio = if ratio >= 1.0 {
+        ratio.round()
+    } else {
+        1.0 / (1.0 / ratio).round()
+    };
+    ratio.clamp(min, max)
+}
+
+/// Next integer ratio up (zooming in) or down (zooming out) from `ratio`, within the zoom limits.
+fn step_ratio(ratio: f32, zoom_in: bool, scale_factor: f32) -> f32 {
+    let (min, max) = integer_ratio_range(scale_factor);
+    let ratio = snap_ratio(ratio, scale_factor);
+    let ratio = match (zoom_in, ratio >= 1.0) {
+        (true, true) => ratio + 1.0,
+        (true, false) => 1.0 / (1.0 / ratio - 1.0),
+        (false, true) if ratio > 1.0 => ratio - 1.0,
+        (false, _) => 1.0 / (1.0 / ratio + 1.0),
+    };
+    ratio.clamp(min, max)
+}
+
+/// World units the world sprite spans.
+fn world_extent(world: &PixelWorld, world_transform: &Transform) -> Vec2 {
+    world.size.as_vec2() * world_transform.scale.truncate().abs()
+}
+
+/// Where the camera sits to look at the middle of the world.
+fn world_center(world_transform: &Transform) -> Vec2 {
+    world_transform.translation.truncate()
+}
+
+/// Scale at which the whole world just fits a window of `window_size` logical pixels.
+pub fn fit_scale(world: &PixelWorld, world_transform: &Transform, window_size: Vec2) -> f32 {
+    (world_extent(world, world_transform) / window_size).max_element()
+}
+
+/// Like [`fit_scale`], rounded out to the closest integer pixel ratio when `settings` ask for one.
+fn fitted_scale(world: &PixelWorld, world_transform: &Transform, window_size: Vec2, scale_factor: f32, settings: &CameraSettings) -> f32 {
+    let scale = fit_scale(world, world_transform, window_size);
+    if !settings.integer_scale {
+        return scale;
     }
+    let ratio = pixel_ratio(scale, scale_factor);
+    let ratio = if ratio >= 1.0 {
+        ratio.floor()
+    } else {
+        1.0 / (1.0 / ratio).ceil()
+    };
+    scale_factor / ratio
+}
+
+/// Run condition for the camera moves of other input plugins, like pinching or the right stick.
+pub fn camera_controller_enabled(config: Res<PixelWorldConfig>) -> bool {
+    config.camera_controller
+}
+
+fn window_size(window: &Window) -> Vec2 {
+    Vec2::new(window.width(), window.height())
+}
+
+/// World position under `cursor` (in logical window pixels) for a camera at `translation` with `scale`.
+fn cursor_world_pos(cursor: Vec2, window_size: Vec2, translation: Vec2, scale: f32) -> Vec2 {
+    translation + (cursor - window_size / 2.0) * Vec2::new(1.0, -1.0) * scale
 }
 
 fn camera_controller(
     time: Res<Time>,
-    keyboard_input: Res<Input<KeyCode>>,
-    mut mouse_wheel_events: EventReader<MouseWheel>,
-    mut query: Query<(&mut Transform, &mut OrthographicProjection), With<Camera>>,
-) 
+    actions: Res<ActionState>,
+    virtual_cursor: Res<VirtualCursor>,
+    settings: Res<CameraSettings>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut query: Query<(&mut Transform, &mut OrthographicProjection), With<PixelWorldCamera>>,
+)
 {
+    let Ok(window) = window_query.get_single() else { return };
     for (mut transform, mut ortho) in query.iter_mut() {
-        let up = keyboard_input.pressed(KeyCode::W);
-        let down = keyboard_input.pressed(KeyCode::S);
-        let left = keyboard_input.pressed(KeyCode::A);
-        let right = keyboard_input.pressed(KeyCode::D);
+        let up = actions.pressed(Action::PanUp);
+        let down = actions.pressed(Action::PanDown);
+        let left = actions.pressed(Action::PanLeft);
+        let right = actions.pressed(Action::PanRight);
 
         let x_axis = right as i8 - left as i8;
         let y_axis = up as i8 - down as i8;
@@ -34,7 +137,7 @@ fn camera_controller(
 
             let z = transform.translation.z;
             transform.translation +=
-                move_delta.extend(z) * CAMERA_MOVE_SPEED * time.delta_seconds();
+                move_delta.extend(z) * CAMERA_MOVE_SPEED * ortho.scale * time.delta_seconds();
 
             // Important! We need to restore the Z values when moving the camera around.
             // Bevy has a specific camera setup and this can mess with how our layers are shown.
@@ -42,33 +145,183 @@ fn camera_controller(
         }
 
         // =========== Zoom =========== //
-        for MouseWheel { x, y, unit, window } in mouse_wheel_events.read() {
-            let mut x_scroll_diff = 0.0;
-            let mut y_scroll_diff = 0.0;
-
-            match unit {
-                MouseScrollUnit::Line => {
-                    x_scroll_diff += x;
-                    y_scroll_diff += y;
-                }
-                MouseScrollUnit::Pixel => {
-                    // I just took this from three-rs, no idea why this magic number was chosen ¯\_(ツ)_/¯
-                    const PIXELS_PER_LINE: f32 = 38.0;
-
-                    y_scroll_diff += y / PIXELS_PER_LINE;
-                    x_scroll_diff += x / PIXELS_PER_LINE;
-                }
+        // integer steps are far apart, so they are taken once per press instead of every frame
+        let (zoom_in, zoom_out) = if settings.integer_scale {
+            (actions.just_pressed(Action::ZoomIn), actions.just_pressed(Action::ZoomOut))
+        } else {
+            (actions.pressed(Action::ZoomIn), actions.pressed(Action::ZoomOut))
+        };
+        if zoom_in != zoom_out {
+            let old_scale = ortho.scale;
+            // one step per wheel notch, a held key steps once a frame
+            let action = if zoom_in { Action::ZoomIn } else { Action::ZoomOut };
+            let steps = actions.wheel_notches(action).max(1);
+            if settings.integer_scale {
+                let scale_factor = window.scale_factor() as f32;
+                let ratio = (0..steps).fold(pixel_ratio(ortho.scale, scale_factor), |ratio, _| step_ratio(ratio, zoom_in, scale_factor));
+                ortho.scale = scale_factor / ratio;
+            } else if zoom_out {
+                ortho.scale *= ZOOM_STEP.powi(steps as i32);
+            } else {
+                ortho.scale *= (1.0 / ZOOM_STEP).powi(steps as i32);
             }
 
-            if x_scroll_diff != 0.0 || y_scroll_diff != 0.0 {
-                if y_scroll_diff < 0.0 {
-                    ortho.scale *= 1.05;
-                } else {
-                    ortho.scale *= 1.0 / 1.05;
-                }
+            ortho.scale = ortho.scale.clamp(MIN_ZOOM, MAX_ZOOM);
 
-                ortho.scale = ortho.scale.clamp(0.15, 5.);
+            // keep the point under the cursor where it is
+            let cursor = if virtual_cursor.active {
+                virtual_cursor.position
+            } else {
+                window.cursor_position()
+            };
+            if let Some(cursor) = cursor {
+                let translation = transform.translation.truncate();
+                let anchor = cursor_world_pos(cursor, window_size(window), translation, old_scale);
+                let translation = anchor - (anchor - translation) * ortho.scale / old_scale;
+                transform.translation.x = translation.x;
+                transform.translation.y = translation.y;
             }
         }
     }
-}
\ No newline at end of file
+}
+
+fn drag_pan(
+    actions: Res<ActionState>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut query: Query<(&Camera, &GlobalTransform, &mut Transform), With<PixelWorldCamera>>,
+    // cursor position the drag last moved the camera for
+    mut last_cursor: Local<Option<Vec2>>,
+) {
+    let cursor = window_query.get_single().ok().and_then(Window::cursor_position);
+    if !actions.pressed(Action::DragPan) {
+        *last_cursor = None;
+        return;
+    }
+    let (Some(previous), Some(cursor)) = (std::mem::replace(&mut *last_cursor, cursor), cursor) else { return };
+    if previous == cursor {
+        return;
+    }
+    for (camera, camera_transform, mut transform) in query.iter_mut() {
+        // the world point that was under the cursor moves along with it
+        let (Some(from), Some(to)) = (
+            camera.viewport_to_world_2d(camera_transform, previous),
+            camera.viewport_to_world_2d(camera_transform, cursor),
+        ) else {
+            continue;
+        };
+        let pan = from - to;
+        transform.translation.x += pan.x;
+        transform.translation.y += pan.y;
+    }
+}
+
+fn fit_world_shortcut(
+    actions: Res<ActionState>,
+    settings: Res<CameraSettings>,
+    primary: Query<(&PixelWorld, &Transform), (With<PrimaryPixelWorld>, Without<PixelWorldCamera>)>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut query: Query<(&mut Transform, &mut OrthographicProjection), With<PixelWorldCamera>>,
+) {
+    if !actions.just_pressed(Action::FitWorld) {
+        return;
+    }
+    let Ok(window) = window_query.get_single() else { return };
+    let Ok((world, world_transform)) = primary.get_single() else { return };
+    let center = world_center(world_transform);
+    for (mut transform, mut ortho) in query.iter_mut() {
+        ortho.scale = fitted_scale(world, world_transform, window_size(window), window.scale_factor() as f32, &settings);
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+    }
+}
+
+fn toggle_integer_scale(actions: Res<ActionState>, mut settings: ResMut<CameraSettings>) {
+    if actions.just_pressed(Action::ToggleIntegerScale) {
+        settings.integer_scale = !settings.integer_scale;
+    }
+}
+
+// a camera showing the whole world keeps doing so when the window changes size
+fn fit_on_resize(
+    mut settings: ResMut<CameraSettings>,
+    primary: Query<(&PixelWorld, &Transform), (With<PrimaryPixelWorld>, Without<PixelWorldCamera>)>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut query: Query<(&mut Transform, &mut OrthographicProjection), With<PixelWorldCamera>>,
+) {
+    let Ok(window) = window_query.get_single() else { return };
+    let scale_factor = window.scale_factor() as f32;
+    let window_size = window_size(window);
+    let Ok((world, world_transform)) = primary.get_single() else { return };
+    let center = world_center(world_transform);
+    // minimized
+    if window_size.min_element() <= 0.0 {
+        return;
+    }
+    let Some(last_window_size) = settings.last_window_size.replace(window_size) else {
+        // first frame, start out fitted
+        for (mut transform, mut ortho) in query.iter_mut() {
+            ortho.scale = fitted_scale(world, world_transform, window_size, scale_factor, &settings);
+            transform.translation.x = center.x;
+            transform.translation.y = center.y;
+        }
+        return;
+    };
+    if last_window_size == window_size {
+        return;
+    }
+
+    for (transform, mut ortho) in query.iter_mut() {
+        // snap_camera may have moved a fitted camera onto the pixel grid, by up to a physical pixel
+        let pixel = ortho.scale / scale_factor;
+        let was_fitted = transform.translation.truncate().distance(center) <= pixel
+            && (ortho.scale - fitted_scale(world, world_transform, last_window_size, scale_factor, &settings)).abs() < 1e-3;
+        if was_fitted {
+            ortho.scale = fitted_scale(world, world_transform, window_size, scale_factor, &settings);
+        }
+    }
+}
+
+// with integer scaling on, undo whatever continuous zoom (pinching, resizing) happened this frame
+// and line the camera up with the screen pixels
+fn snap_camera(
+    settings: Res<CameraSettings>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut query: Query<(&mut Transform, &mut OrthographicProjection), With<PixelWorldCamera>>,
+) {
+    if !settings.integer_scale {
+        return;
+    }
+    let Ok(window) = window_query.get_single() else { return };
+    let scale_factor = window.scale_factor() as f32;
+    for (mut transform, mut ortho) in query.iter_mut() {
+        let scale = scale_factor / snap_ratio(pixel_ratio(ortho.scale, scale_factor), scale_factor);
+        if ortho.scale != scale {
+            ortho.scale = scale;
+        }
+        // world units per physical pixel
+        let pixel = scale / scale_factor;
+        let translation = (transform.translation.truncate() / pixel).round() * pixel;
+        if translation != transform.translation.truncate() {
+            transform.translation.x = translation.x;
+            transform.translation.y = translation.y;
+        }
+    }
+}
+
+// the camera center may not leave the world, so part of it is always on screen
+fn clamp_camera(
+    primary: Query<(&PixelWorld, &Transform), (With<PrimaryPixelWorld>, Without<PixelWorldCamera>)>,
+    mut query: Query<&mut Transform, With<PixelWorldCamera>>,
+) {
+    let Ok((world, world_transform)) = primary.get_single() else { return };
+    let half_world = world_extent(world, world_transform) / 2.0;
+    let center = world_center(world_transform);
+    for mut transform in query.iter_mut() {
+        let translation = transform.translation.truncate();
+        let clamped = translation.clamp(center - half_world, center + half_world);
+        if clamped != translation {
+            transform.translation.x = clamped.x;
+            transform.translation.y = clamped.y;
+        }
+    }
+}
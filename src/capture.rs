@@ -0,0 +1,259 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::PathBuf,
+    sync::mpsc::{sync_channel, SyncSender, TrySendError},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{prelude::*, tasks::IoTaskPool};
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame, ImageResult, RgbaImage,
+};
+
+use crate::{
+    actions::{Action, ActionState},
+    input::update_input_state,
+    pipeline::transfer::{ReadbackFailed, ReadbackId, ReadbackRequests, RegionReadback},
+    PixelWorld, PrimaryPixelWorld,
+};
+
+const CAPTURE_FOLDER: &str = "captures";
+/// Ticks per second a GIF plays back at when the world has no `tick_rate` and ticks once per rendered frame.
+const FRAME_TICKS_PER_SECOND: f32 = 60.0;
+/// 1 is the best and slowest color quantization, 30 the fastest.
+const GIF_SPEED: i32 = 10;
+/// Frames waiting for the GIF encoder, further frames are dropped until it catches up.
+const GIF_QUEUE_FRAMES: usize = 8;
+
+/// Screenshots (F12) and recordings (F9 for numbered PNGs, Shift+F9 for a GIF) of the world
+/// at simulation resolution, written to the `captures` folder next to `assets`.
+pub struct CapturePlugin;
+
+impl Plugin for CapturePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Capture>().add_systems(
+            Update,
+            (toggle_recording, request_captures, write_captures)
+                .chain()
+                .after(update_input_state),
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct Capture {
+    /// Recordings keep one frame every this many ticks of the primary world
+    pub every_ticks: u32,
+    recording: Option<Recording>,
+    /// Encoder of a stopped GIF recording, dropped to finish the GIF once its last frames arrived
+    finishing_gif: Option<SyncSender<RgbaImage>>,
+    /// Readbacks in flight and where their frame goes
+    pending: Vec<(ReadbackId, CaptureTarget)>,
+}
+
+impl Default for Capture {
+    fn default() -> Self {
+        Self {
+            every_ticks: 4,
+            recording: None,
+            finishing_gif: None,
+            pending: Vec::new(),
+        }
+    }
+}
+
+struct Recording {
+    output: RecordingOutput,
+    /// Tick of the primary world the next frame is kept at
+    next_tick: u64,
+    frames: u32,
+}
+
+enum RecordingOutput {
+    /// Folder the numbered frames go into
+    Png(PathBuf),
+    /// Frames are encoded on a separate thread, the GIF is finished once the sender is dropped
+    Gif(SyncSender<RgbaImage>),
+}
+
+enum CaptureTarget {
+    Png(PathBuf),
+    /// The GIF being recorded, or the one finishing if the recording stopped since
+    Gif,
+}
+
+fn capture_folder() -> PathBuf {
+    bevy::asset::io::file::FileAssetReader::get_base_path().join(CAPTURE_FOLDER)
+}
+
+fn timestamp() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or_default()
+}
+
+fn toggle_recording(
+    actions: Res<ActionState>,
+    primary: Query<&PixelWorld, With<PrimaryPixelWorld>>,
+    mut capture: ResMut<Capture>,
+) {
+    let png = actions.just_pressed(Action::ToggleRecording);
+    let gif = actions.just_pressed(Action::ToggleGifRecording);
+    if !png && !gif {
+        return;
+    }
+
+    if let Some(recording) = capture.recording.take() {
+        info!("Stopped recording after {} frames", recording.frames);
+        if let RecordingOutput::Gif(sender) = recording.output {
+            let waiting = capture.pending.iter().any(|(_, target)| matches!(target, CaptureTarget::Gif));
+            capture.finishing_gif = waiting.then_some(sender);
+        }
+        return;
+    }
+
+    let Ok(world) = primary.get_single() else { return };
+    let name = format!("recording_{}", timestamp());
+    let output = if gif {
+        // frames still on their way to an earlier GIF would end up in this one
+        capture.pending.retain(|(_, target)| !matches!(target, CaptureTarget::Gif));
+        capture.finishing_gif = None;
+        let path = capture_folder().join(format!("{name}.gif"));
+        let ticks_per_second = world.clock.tick_rate.unwrap_or(FRAME_TICKS_PER_SECOND);
+        let delay = Delay::from_saturating_duration(Duration::from_secs_f32(
+            capture.every_ticks.max(1) as f32 / ticks_per_second.max(f32::EPSILON),
+        ));
+        RecordingOutput::Gif(spawn_gif_writer(path, delay))
+    } else {
+        let folder = capture_folder().join(name);
+        if let Err(error) = std::fs::create_dir_all(&folder) {
+            warn!("Failed to create {} {error:?}", folder.display());
+            return;
+        }
+        RecordingOutput::Png(folder)
+    };
+    info!("Recording every {} ticks", capture.every_ticks);
+    capture.recording = Some(Recording {
+        output,
+        next_tick: world.clock.ticks,
+        frames: 0,
+    });
+}
+
+fn spawn_gif_writer(path: PathBuf, delay: Delay) -> SyncSender<RgbaImage> {
+    let (sender, receiver) = sync_channel::<RgbaImage>(GIF_QUEUE_FRAMES);
+    std::thread::spawn(move || {
+        let encode = || -> ImageResult<()> {
+            std::fs::create_dir_all(capture_folder())?;
+            let mut encoder = GifEncoder::new_with_speed(BufWriter::new(File::create(&path)?), GIF_SPEED);
+            encoder.set_repeat(Repeat::Infinite)?;
+            for frame in receiver {
+                encoder.encode_frame(Frame::from_parts(frame, 0, 0, delay))?;
+            }
+            Ok(())
+        };
+        match encode() {
+            Ok(()) => info!("Saved recording {}", path.display()),
+            Err(error) => warn!("Failed to save recording {} {error:?}", path.display()),
+        }
+    });
+    sender
+}
+
+// the whole world is read back, the frame lands in `write_captures` a frame or two later
+fn request_captures(
+    actions: Res<ActionState>,
+    primary: Query<(Entity, &PixelWorld), With<PrimaryPixelWorld>>,
+    mut capture: ResMut<Capture>,
+    mut readbacks: ResMut<ReadbackRequests>,
+) {
+    let Ok((world, pixel_world)) = primary.get_single() else { return };
+    let region = pixel_world.rect();
+
+    if actions.just_pressed(Action::Screenshot) {
+        let path = capture_folder().join(format!("screenshot_{}.png", timestamp()));
+        let id = readbacks.request(world, region);
+        capture.pending.push((id, CaptureTarget::Png(path)));
+    }
+
+    let every_ticks = capture.every_ticks.max(1) as u64;
+    let Some(recording) = &mut capture.recording else { return };
+    let ticks = pixel_world.clock.ticks;
+    if ticks < recording.next_tick {
+        return;
+    }
+    // a world ticking several times a frame keeps the frames on its every_ticks grid
+    recording.next_tick += (ticks - recording.next_tick) / every_ticks * every_ticks + every_ticks;
+
+    let target = match &recording.output {
+        RecordingOutput::Png(folder) => CaptureTarget::Png(folder.join(format!("frame_{:05}.png", recording.frames))),
+        RecordingOutput::Gif(_) => CaptureTarget::Gif,
+    };
+    recording.frames += 1;
+    let id = readbacks.request(world, region);
+    capture.pending.push((id, target));
+}
+
+fn write_captures(
+    mut readbacks: EventReader<RegionReadback>,
+    mut failed: EventReader<ReadbackFailed>,
+    mut capture: ResMut<Capture>,
+) {
+    for failed in failed.read() {
+        capture.pending.retain(|(id, _)| *id != failed.id);
+    }
+
+    for readback in readbacks.read() {
+        let Some(index) = capture.pending.iter().position(|(id, _)| *id == readback.id) else {
+            continue;
+        };
+        let (_, target) = capture.pending.remove(index);
+        let size = readback.region.size();
+        let Some(frame) = RgbaImage::from_raw(size.x, size.y, readback.data.clone()) else {
+            continue;
+        };
+
+        match target {
+            CaptureTarget::Png(path) => {
+                IoTaskPool::get()
+                    .spawn(async move {
+                        let saved = path
+                            .parent()
+                            .map_or(Ok(()), std::fs::create_dir_all)
+                            .map_err(image::ImageError::from)
+                            .and_then(|_| frame.save(&path));
+                        if let Err(error) = saved {
+                            warn!("Failed to save capture {} {error:?}", path.display());
+                        }
+                    })
+                    .detach();
+            }
+            CaptureTarget::Gif => {
+                let sender = match &capture.recording {
+                    Some(Recording {
+                        output: RecordingOutput::Gif(sender),
+                        ..
+                    }) => Some(sender),
+                    _ => capture.finishing_gif.as_ref(),
+                };
+                let Some(sender) = sender else { continue };
+                match sender.try_send(frame) {
+                    Ok(()) => {}
+                    // encoding is slower than recording, skip frames rather than queue them all in memory
+                    Err(TrySendError::Full(_)) => warn!("GIF encoder is behind, dropped a recorded frame"),
+                    // the writer only goes away if encoding failed, which it already warned about
+                    Err(TrySendError::Disconnected(_)) => {}
+                }
+            }
+        }
+    }
+
+    // every frame of the stopped GIF arrived or failed, finish it
+    let gif_pending = capture.pending.iter().any(|(_, target)| matches!(target, CaptureTarget::Gif));
+    if !gif_pending {
+        capture.finishing_gif = None;
+    }
+}
@@ -0,0 +1,273 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+};
+
+use crate::{
+    actions::{Action, ActionState},
+    brush::{BrushMaterial, BrushMode},
+    grid::CellGrid,
+    input::{update_input_state, DrawingParams},
+    pipeline::transfer::{ReadbackFailed, ReadbackId, ReadbackRequests, RegionReadback, RegionWrite, RegionWrites},
+    tools::{DrawShape, DrawTool, ShapeKind},
+    CurrentElement, PixelWorld, PixelWorldConfig, PrimaryPixelWorld,
+};
+
+const SELECTION_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+const GHOST_TINT: Color = Color::rgba(1.0, 1.0, 1.0, 0.6);
+
+/// Rectangular selections of the world that can be copied, cut and pasted back at the cursor.
+pub struct ClipboardPlugin;
+
+impl Plugin for ClipboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Clipboard>().add_systems(
+            Update,
+            (
+                select_region,
+                clipboard_shortcuts,
+                receive_copy,
+                place_paste,
+                update_paste_ghost,
+                preview_selection,
+            )
+                .chain()
+                .after(update_input_state),
+        );
+    }
+}
+
+#[derive(Default, Resource)]
+pub struct Clipboard {
+    /// Cells last copied or cut, in the orientation they will be pasted in
+    pub grid: Option<CellGrid>,
+    /// Selected region of the world, in canvas cells
+    pub selection: Option<URect>,
+    drag_start: Option<Vec2>,
+    pending_copy: Option<ReadbackId>,
+    /// The ghost sprite shows an outdated grid
+    ghost_dirty: bool,
+}
+
+impl Clipboard {
+    pub fn set_grid(&mut self, grid: CellGrid) {
+        self.grid = Some(grid);
+        self.ghost_dirty = true;
+    }
+
+    /// Top left cell the clipboard lands on when pasted centered on `cursor`.
+    pub fn paste_origin(&self, cursor: Vec2) -> Option<IVec2> {
+        let grid = self.grid.as_ref()?;
+        Some((cursor - grid.size.as_vec2() / 2.0).round().as_ivec2())
+    }
+}
+
+#[derive(Component)]
+struct PasteGhost;
+
+/// Cells covered by dragging from `a` to `b`, both included, clamped to the world.
+pub fn selection_rect(a: Vec2, b: Vec2, world_size: UVec2) -> URect {
+    let world = world_size.as_vec2();
+    let min = a.min(b).floor().clamp(Vec2::ZERO, world);
+    let max = (a.max(b).floor() + Vec2::ONE).clamp(Vec2::ZERO, world);
+    URect::from_corners(min.as_uvec2(), max.as_uvec2())
+}
+
+fn select_region(
+    actions: Res<ActionState>,
+    params: Res<DrawingParams>,
+    primary: Query<&PixelWorld, With<PrimaryPixelWorld>>,
+    mut clipboard: ResMut<Clipboard>,
+) {
+    if params.tool != DrawTool::Select {
+        clipboard.drag_start = None;
+        return;
+    }
+
+    if actions.just_pressed(Action::Draw) && !params.pointer_over_ui {
+        clipboard.drag_start = Some(params.mouse_pos);
+    }
+    let Ok(world) = primary.get_single() else { return };
+    if let Some(start) = clipboard.drag_start {
+        let selection = selection_rect(start, params.mouse_pos, world.size);
+        clipboard.selection = (!selection.is_empty()).then_some(selection);
+    }
+    if actions.just_released(Action::Draw) {
+        clipboard.drag_start = None;
+    }
+}
+
+fn clipboard_shortcuts(
+    actions: Res<ActionState>,
+    mut clipboard: ResMut<Clipboard>,
+    mut params: ResMut<DrawingParams>,
+    mut readbacks: ResMut<ReadbackRequests>,
+    primary: Query<Entity, With<PrimaryPixelWorld>>,
+) {
+    let cut = actions.just_pressed(Action::Cut);
+    if actions.just_pressed(Action::Copy) || cut {
+        if let (Some(selection), Ok(world)) = (clipboard.selection, primary.get_single()) {
+            clipboard.pending_copy = Some(readbacks.request(world, selection));
+
+            // the readback copies the world before anything is drawn this frame, so the cut can happen right away
+            if cut {
+                params.shapes.push(DrawShape {
+                    kind: ShapeKind::Rectangle,
+                    start: selection.min.as_vec2(),
+                    end: (selection.max - UVec2::ONE).as_vec2(),
+                    thickness: 1.0,
+                    hollow: false,
+                    element: CurrentElement::AIR,
+                    material: BrushMaterial::Single,
+                    mode: BrushMode::Overwrite,
+                });
+            }
+        }
+    }
+
+    if actions.just_pressed(Action::Paste) && clipboard.grid.is_some() {
+        params.tool = DrawTool::Paste;
+    }
+
+    if actions.just_pressed(Action::Cancel) {
+        match params.tool {
+            DrawTool::Paste => params.tool = DrawTool::Brush,
+            DrawTool::Select => clipboard.selection = None,
+            _ => {}
+        }
+    }
+
+    if params.tool == DrawTool::Paste {
+        let transformed = clipboard.grid.as_ref().and_then(|grid| {
+            if actions.just_pressed(Action::RotateClipboard) {
+                Some(grid.rotated())
+            } else if actions.just_pressed(Action::FlipClipboard) {
+                Some(grid.flipped())
+            } else {
+                None
+            }
+        });
+        if let Some(grid) = transformed {
+            clipboard.set_grid(grid);
+        }
+    }
+}
+
+fn receive_copy(
+    mut readbacks: EventReader<RegionReadback>,
+    mut failed: EventReader<ReadbackFailed>,
+    mut clipboard: ResMut<Clipboard>,
+) {
+    if failed.read().any(|failed| clipboard.pending_copy == Some(failed.id)) {
+        clipboard.pending_copy = None;
+    }
+    for readback in readbacks.read() {
+        if clipboard.pending_copy == Some(readback.id) {
+            clipboard.pending_copy = None;
+            clipboard.set_grid(readback.to_grid());
+        }
+    }
+}
+
+fn place_paste(
+    actions: Res<ActionState>,
+    params: Res<DrawingParams>,
+    clipboard: Res<Clipboard>,
+    primary: Query<(Entity, &PixelWorld), With<PrimaryPixelWorld>>,
+    mut writes: ResMut<RegionWrites>,
+) {
+    if params.tool != DrawTool::Paste || !actions.just_pressed(Action::Draw) || params.pointer_over_ui {
+        return;
+    }
+    let (Some(grid), Some(origin)) = (&clipboard.grid, clipboard.paste_origin(params.mouse_pos)) else {
+        return;
+    };
+    let Ok((entity, world)) = primary.get_single() else { return };
+
+    if let Some((origin, grid)) = grid.clipped(origin, world.rect()) {
+        writes.0.push(RegionWrite { world: entity, origin, grid });
+    }
+}
+
+fn update_paste_ghost(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut clipboard: ResMut<Clipboard>,
+    params: Res<DrawingParams>,
+    config: Res<PixelWorldConfig>,
+    primary: Query<(&PixelWorld, &Transform), (With<PrimaryPixelWorld>, Without<PasteGhost>)>,
+    mut ghosts: Query<(Entity, &mut Transform, &Handle<Image>), With<PasteGhost>>,
+) {
+    let grid = match (&clipboard.grid, params.tool) {
+        (Some(grid), DrawTool::Paste) => grid,
+        _ => {
+            for (entity, ..) in &ghosts {
+                commands.entity(entity).despawn();
+            }
+            clipboard.ghost_dirty = true;
+            return;
+        }
+    };
+
+    let Ok((world, world_transform)) = primary.get_single() else { return };
+    let origin = clipboard.paste_origin(params.mouse_pos).unwrap();
+    let center = world.canvas_to_world(world_transform, origin.as_vec2() + grid.size.as_vec2() / 2.0);
+    let transform = Transform {
+        translation: center.extend(world_transform.translation.z + 1.0),
+        ..*world_transform
+    };
+
+    if let Ok((_, mut ghost_transform, image)) = ghosts.get_single_mut() {
+        *ghost_transform = transform;
+        if clipboard.ghost_dirty {
+            images.insert(image, ghost_image(grid));
+            clipboard.ghost_dirty = false;
+        }
+        return;
+    }
+
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: GHOST_TINT,
+                ..default()
+            },
+            texture: images.add(ghost_image(grid)),
+            transform,
+            ..default()
+        },
+        PasteGhost,
+        config.render_layers,
+    ));
+    clipboard.ghost_dirty = false;
+}
+
+fn ghost_image(grid: &CellGrid) -> Image {
+    let mut image = Image::new(
+        Extent3d {
+            width: grid.size.x,
+            height: grid.size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        grid.to_rgba(),
+        TextureFormat::Rgba8Unorm,
+    );
+    image.sampler = ImageSampler::nearest();
+    image
+}
+
+fn preview_selection(
+    mut gizmos: Gizmos,
+    clipboard: Res<Clipboard>,
+    primary: Query<(&PixelWorld, &Transform), With<PrimaryPixelWorld>>,
+) {
+    let Some(selection) = clipboard.selection else { return };
+    let Ok((world, world_transform)) = primary.get_single() else { return };
+
+    let center = world.canvas_to_world(world_transform, selection.min.as_vec2() + selection.size().as_vec2() / 2.0);
+    gizmos.rect_2d(center, 0.0, selection.size().as_vec2() * world_transform.scale.truncate(), SELECTION_COLOR);
+}
@@ -0,0 +1,167 @@
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    render::extract_resource::{ExtractResource, ExtractResourcePlugin},
+};
+
+use crate::{
+    brush::{BrushMaterial, BrushMode},
+    tools::{DrawShape, ShapeKind},
+    CurrentElement, PixelWorld, PrimaryPixelWorld,
+};
+
+/// Smallest radius of lines and circles, smaller ones are widened to it so they paint at least
+/// the cell at a whole-numbered position instead of nothing.
+pub const MIN_RADIUS: f32 = 0.5;
+
+/// Lets any system edit the world through [`PixelWorldCommands`].
+pub struct WorldCommandsPlugin;
+
+impl Plugin for WorldCommandsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldEdits>()
+            .add_plugins(ExtractResourcePlugin::<WorldEdits>::default())
+            .add_systems(First, clear_world_edits);
+    }
+}
+
+/// A shape drawn into the [`PixelWorld`] on `world`.
+#[derive(Clone, Debug)]
+pub struct WorldEdit {
+    pub world: Entity,
+    pub shape: DrawShape,
+}
+
+/// Edits queued by [`PixelWorldCommands`] this frame, drawn by the draw pass after the player's own drawing.
+#[derive(Default, Resource, ExtractResource, Clone)]
+pub struct WorldEdits(pub(crate) Vec<WorldEdit>);
+
+impl WorldEdits {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Shapes queued for the world on `world`, in the order they were made.
+    pub fn shapes(&self, world: Entity) -> impl Iterator<Item = &DrawShape> {
+        self.0.iter().filter(move |edit| edit.world == world).map(|edit| &edit.shape)
+    }
+}
+
+// edits only live for the frame they were queued in
+fn clear_world_edits(mut edits: ResMut<WorldEdits>) {
+    if !edits.0.is_empty() {
+        edits.0.clear();
+    }
+}
+
+/// Edits the worlds from any system, e.g. to spawn explosions, rain or level pieces.
+///
+/// The methods here edit the [`PrimaryPixelWorld`], [`PixelWorldCommands::world`] edits any other one.
+/// Positions are canvas cells, `(0, 0)` being the top left cell of the world,
+/// convert from world space with [`PixelWorld::world_to_canvas`].
+/// Edits land at the end of the frame in the order they were made, cells outside the world are ignored.
+/// All edits of a world are drawn together in a single pass of the draw shader.
+#[derive(SystemParam)]
+pub struct PixelWorldCommands<'w, 's> {
+    edits: ResMut<'w, WorldEdits>,
+    worlds: Query<'w, 's, (Entity, &'static PixelWorld, Has<PrimaryPixelWorld>)>,
+}
+
+impl<'w, 's> PixelWorldCommands<'w, 's> {
+    /// Edits the [`PixelWorld`] on `world`, edits of an entity without one are dropped.
+    pub fn world(&mut self, world: Entity) -> PixelWorldEditor<'_> {
+        let size = self.worlds.get(world).ok().map(|(_, pixel_world, _)| pixel_world.size);
+        PixelWorldEditor {
+            edits: &mut self.edits,
+            world,
+            size,
+        }
+    }
+
+    /// Edits the [`PrimaryPixelWorld`].
+    pub fn primary(&mut self) -> PixelWorldEditor<'_> {
+        let primary = self.worlds.iter().find_map(|(entity, _, primary)| primary.then_some(entity));
+        self.world(primary.unwrap_or(Entity::PLACEHOLDER))
+    }
+
+    pub fn set_cell(&mut self, cell: IVec2, element: CurrentElement) {
+        self.primary().set_cell(cell, element);
+    }
+
+    /// Fills the cells from `rect.min` up to, but not including, `rect.max`.
+    pub fn fill_rect(&mut self, rect: IRect, element: CurrentElement) {
+        self.primary().fill_rect(rect, element);
+    }
+
+    /// Paints every cell within `radius` of the segment from `start` to `end`, see [`MIN_RADIUS`].
+    pub fn draw_line(&mut self, start: Vec2, end: Vec2, radius: f32, element: CurrentElement) {
+        self.primary().draw_line(start, end, radius, element);
+    }
+
+    /// Paints every cell within `radius` of `center`, see [`MIN_RADIUS`].
+    pub fn spawn_circle(&mut self, center: Vec2, radius: f32, element: CurrentElement) {
+        self.primary().spawn_circle(center, radius, element);
+    }
+
+    /// Fills the whole world with air.
+    pub fn clear(&mut self) {
+        self.primary().clear();
+    }
+}
+
+/// Edits one world, see [`PixelWorldCommands::world`].
+pub struct PixelWorldEditor<'a> {
+    edits: &'a mut WorldEdits,
+    world: Entity,
+    /// `None` when the entity holds no world
+    size: Option<UVec2>,
+}
+
+impl<'a> PixelWorldEditor<'a> {
+    fn push(&mut self, kind: ShapeKind, start: Vec2, end: Vec2, thickness: f32, element: CurrentElement) {
+        if self.size.is_none() {
+            return;
+        }
+        self.edits.0.push(WorldEdit {
+            world: self.world,
+            shape: DrawShape {
+                kind,
+                start,
+                end,
+                thickness,
+                hollow: false,
+                element,
+                material: BrushMaterial::Single,
+                mode: BrushMode::Overwrite,
+            },
+        });
+    }
+
+    pub fn set_cell(&mut self, cell: IVec2, element: CurrentElement) {
+        self.fill_rect(IRect::from_corners(cell, cell + IVec2::ONE), element);
+    }
+
+    /// Fills the cells from `rect.min` up to, but not including, `rect.max`.
+    pub fn fill_rect(&mut self, rect: IRect, element: CurrentElement) {
+        if rect.is_empty() {
+            return;
+        }
+        self.push(ShapeKind::Rectangle, rect.min.as_vec2(), (rect.max - IVec2::ONE).as_vec2(), 0.0, element);
+    }
+
+    /// Paints every cell within `radius` of the segment from `start` to `end`, see [`MIN_RADIUS`].
+    pub fn draw_line(&mut self, start: Vec2, end: Vec2, radius: f32, element: CurrentElement) {
+        self.push(ShapeKind::Line, start, end, radius.max(MIN_RADIUS), element);
+    }
+
+    /// Paints every cell within `radius` of `center`, see [`MIN_RADIUS`].
+    pub fn spawn_circle(&mut self, center: Vec2, radius: f32, element: CurrentElement) {
+        self.push(ShapeKind::Line, center, center, radius.max(MIN_RADIUS), element);
+    }
+
+    /// Fills the whole world with air.
+    pub fn clear(&mut self) {
+        let Some(size) = self.size else { return };
+        self.fill_rect(IRect::from_corners(IVec2::ZERO, size.as_ivec2()), CurrentElement::AIR);
+    }
+}
@@ -0,0 +1,38 @@
+use bevy::{prelude::*, render::view::RenderLayers};
+
+/// How [`crate::PixelWorldPlugin`] sets up the app around its worlds, available as a resource in the main world.
+///
+/// The size, placement and rules of every world live on its [`crate::PixelWorld`] entity instead.
+#[derive(Resource, Clone, Debug)]
+pub struct PixelWorldConfig {
+    /// Spawns a 2D camera with [`PixelWorldCamera`], otherwise add that marker to a camera of your own
+    /// for the pointer to find the world
+    pub spawn_camera: bool,
+    /// Drawing with the mouse, touch and gamepad, along with the tools, palette, inspector and bindings panels
+    /// and the hotkeys for the stats HUD, debug views, scene save/load and captures
+    pub input: bool,
+    /// Panning and zooming the [`PixelWorldCamera`], and keeping it over the world
+    pub camera_controller: bool,
+    /// Layers the primary world sprites are drawn on, the spawned camera renders these
+    pub render_layers: RenderLayers,
+    /// Moves every gizmo of the app to `render_layers`, so the brush, shape and selection previews
+    /// show up when the world is drawn on layers of its own. Gizmos are configured globally,
+    /// so this also moves the gizmos of the host game.
+    pub gizmos_on_world_layers: bool,
+}
+
+impl Default for PixelWorldConfig {
+    fn default() -> Self {
+        Self {
+            spawn_camera: true,
+            input: true,
+            camera_controller: true,
+            render_layers: RenderLayers::default(),
+            gizmos_on_world_layers: false,
+        }
+    }
+}
+
+/// Camera the pointer is mapped through and the camera controller moves.
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct PixelWorldCamera;
@@ -0,0 +1,111 @@
+use crate::{grid::CellGrid, worlds::PixelWorldRules, CurrentElement};
+
+fn hash(value: u32) -> u32 {
+    let mut state = value;
+    state ^= 2747636419;
+    state = state.wrapping_mul(2654435769);
+    state ^= state >> 16;
+    state = state.wrapping_mul(2654435769);
+    state ^= state >> 16;
+    state.wrapping_mul(2654435769)
+}
+
+fn random_float(value: u32) -> f32 {
+    hash(value) as f32 / 4294967295.0
+}
+
+/// Sideways direction a particle at `(x, y)` tries first, picked like the shader does when `seed` is 0.
+fn direction(x: u32, y: u32, seed: u32) -> i32 {
+    if random_float(y.wrapping_add(x).wrapping_add(seed)) < 0.5 {
+        -1
+    } else {
+        1
+    }
+}
+
+fn get(grid: &CellGrid, x: i32, y: i32) -> Option<CurrentElement> {
+    if x < 0 || y < 0 || x >= grid.size.x as i32 || y >= grid.size.y as i32 {
+        return None;
+    }
+    Some(grid.get(x as u32, y as u32))
+}
+
+fn is_air(grid: &CellGrid, x: i32, y: i32) -> bool {
+    get(grid, x, y) == Some(CurrentElement::AIR)
+}
+
+/// Cell a particle at `(x, y)` moves to this tick, if any.
+fn destination(grid: &CellGrid, x: i32, y: i32, seed: u32, rules: PixelWorldRules) -> Option<(i32, i32)> {
+    let side = direction(x as u32, y as u32, seed);
+    match grid.get(x as u32, y as u32) {
+        CurrentElement::SAND => {
+            if is_air(grid, x, y + 1) {
+                Some((x, y + 1))
+            } else if rules.sand_slides
+                && get(grid, x, y + 1) == Some(CurrentElement::SAND)
+                && is_air(grid, x + side, y + 1)
+            {
+                Some((x + side, y + 1))
+            } else {
+                None
+            }
+        }
+        CurrentElement::WATER => {
+            if is_air(grid, x, y + 1) {
+                Some((x, y + 1))
+            } else if !rules.water_spreads {
+                None
+            } else if is_air(grid, x + side, y + 1) {
+                Some((x + side, y + 1))
+            } else if is_air(grid, x + side, y) {
+                Some((x + side, y))
+            } else {
+                None
+            }
+        }
+        CurrentElement::AIR | CurrentElement::ROCK => None,
+    }
+}
+
+/// Advances the grid by one tick of the rules in `assets/shaders/game_of_life.wgsl`, without a GPU.
+///
+/// The shader updates every cell in parallel and races when two particles move into the same cell,
+/// this port walks the grid from the bottom row up and moves every particle at most once per tick,
+/// so it never loses particles. Cells outside the grid behave like walls, as they do on the GPU.
+pub fn step(grid: &mut CellGrid) {
+    step_seeded(grid, 0);
+}
+
+/// Like [`step`], with `seed` shifting which way particles try to slide first.
+pub fn step_seeded(grid: &mut CellGrid, seed: u32) {
+    step_with_rules(grid, seed, PixelWorldRules::default());
+}
+
+/// Like [`step_seeded`], moving particles only as far as `rules` allow.
+pub fn step_with_rules(grid: &mut CellGrid, seed: u32, rules: PixelWorldRules) {
+    let size = grid.size.as_ivec2();
+    let mut moved = vec![false; grid.cells.len()];
+
+    for y in (0..size.y).rev() {
+        for x in 0..size.x {
+            let index = (y * size.x + x) as usize;
+            if moved[index] {
+                continue;
+            }
+            let Some((to_x, to_y)) = destination(grid, x, y, seed, rules) else {
+                continue;
+            };
+            let element = grid.get(x as u32, y as u32);
+            grid.set(x as u32, y as u32, CurrentElement::AIR);
+            grid.set(to_x as u32, to_y as u32, element);
+            moved[(to_y * size.x + to_x) as usize] = true;
+        }
+    }
+}
+
+/// Advances the grid by `ticks` automata ticks.
+pub fn run(grid: &mut CellGrid, ticks: u32) {
+    for _ in 0..ticks {
+        step(grid);
+    }
+}
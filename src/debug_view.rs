@@ -0,0 +1,141 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_component::{ExtractComponent, ExtractComponentPlugin},
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        view::RenderLayers,
+    },
+};
+
+use crate::{
+    actions::{Action, ActionState},
+    images,
+    input::input_enabled,
+    PixelWorld,
+};
+
+/// Debug overlays (cycled with F4) drawn over every world by the debug pass, to see what the automata is doing.
+pub struct DebugViewPlugin;
+
+impl Plugin for DebugViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugView>()
+            .add_plugins(ExtractResourcePlugin::<DebugView>::default())
+            .add_plugins(ExtractComponentPlugin::<DebugOverlayImages>::default())
+            .add_systems(Update, (spawn_overlays, cycle_debug_view.run_if(input_enabled), show_overlay).chain());
+    }
+}
+
+/// Which overlay is drawn over the worlds.
+///
+/// The world texture only stores one color per cell, so there are no temperature or velocity views,
+/// and the automata steps every cell every tick, so chunks are "active" when any of their cells changed.
+#[derive(Default, Resource, ExtractResource, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DebugView {
+    #[default]
+    Off,
+    /// Every element in a loud color, cells matching no element in magenta
+    Elements,
+    /// Cells that changed recently, fading out over a few frames
+    Changes,
+    /// Outlines of the 32x32 chunks, bright when a cell inside changed this tick
+    Chunks,
+}
+
+impl DebugView {
+    pub const ALL: [DebugView; 4] = [DebugView::Off, DebugView::Elements, DebugView::Changes, DebugView::Chunks];
+
+    /// View index as understood by the debug shader.
+    pub fn mode(&self) -> u32 {
+        match self {
+            DebugView::Off => 0,
+            DebugView::Elements => 1,
+            DebugView::Changes => 2,
+            DebugView::Chunks => 3,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DebugView::Off => "Off",
+            DebugView::Elements => "Elements",
+            DebugView::Changes => "Changed cells",
+            DebugView::Chunks => "Chunk activity",
+        }
+    }
+
+    pub fn next(&self) -> DebugView {
+        DebugView::ALL[(self.mode() as usize + 1) % DebugView::ALL.len()]
+    }
+}
+
+/// Textures the debug pass draws the overlay of the [`PixelWorld`] on the same entity into,
+/// and keeps the last seen world in.
+#[derive(Component, ExtractComponent, Clone)]
+pub struct DebugOverlayImages {
+    pub overlay: Handle<Image>,
+    pub previous: Handle<Image>,
+}
+
+#[derive(Component)]
+struct DebugOverlay;
+
+// every world gets an overlay sprite as a child, on the layers of the world sprite
+fn spawn_overlays(
+    mut commands: Commands,
+    mut images_res: ResMut<Assets<Image>>,
+    view: Res<DebugView>,
+    worlds: Query<(Entity, &PixelWorld, Option<&RenderLayers>), Added<PixelWorld>>,
+) {
+    for (entity, pixel_world, render_layers) in &worlds {
+        let size = pixel_world.size;
+        let mut overlay = images::create_image(size.x, size.y);
+        overlay.data.fill(0);
+        let overlay = images_res.add(overlay);
+        let previous = images_res.add(images::create_image(size.x, size.y));
+
+        let sprite = commands
+            .spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(size.as_vec2()),
+                        ..default()
+                    },
+                    texture: overlay.clone(),
+                    // just above the world sprite
+                    transform: Transform::from_xyz(0.0, 0.0, 1.0),
+                    visibility: match *view {
+                        DebugView::Off => Visibility::Hidden,
+                        _ => Visibility::Visible,
+                    },
+                    ..default()
+                },
+                DebugOverlay,
+                render_layers.copied().unwrap_or_default(),
+            ))
+            .id();
+        commands
+            .entity(entity)
+            .insert(DebugOverlayImages { overlay, previous })
+            .add_child(sprite);
+    }
+}
+
+fn cycle_debug_view(actions: Res<ActionState>, mut view: ResMut<DebugView>) {
+    if actions.just_pressed(Action::CycleDebugView) {
+        *view = view.next();
+        info!("Debug view: {}", view.name());
+    }
+}
+
+fn show_overlay(view: Res<DebugView>, mut overlay: Query<&mut Visibility, With<DebugOverlay>>) {
+    if !view.is_changed() {
+        return;
+    }
+    for mut visibility in &mut overlay {
+        *visibility = match *view {
+            DebugView::Off => Visibility::Hidden,
+            _ => Visibility::Visible,
+        };
+    }
+}
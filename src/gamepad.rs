@@ -0,0 +1,111 @@
+use bevy::{prelude::*, window::{CursorMoved, PrimaryWindow}};
+
+use crate::{
+    camera::{camera_controller_enabled, CAMERA_MOVE_SPEED},
+    input::update_input_state,
+    PixelWorldCamera,
+};
+
+/// Stick deflection below which the sticks are treated as centered.
+const STICK_DEADZONE: f32 = 0.15;
+/// Logical pixels per second the cursor moves at with the left stick fully deflected.
+const CURSOR_SPEED: f32 = 700.0;
+const CURSOR_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+const CURSOR_SIZE: f32 = 6.0;
+
+/// Drives a virtual cursor with the left stick and pans the camera with the right one.
+/// Drawing, erasing and element cycling go through the action map like every other input.
+pub struct GamepadPlugin;
+
+impl Plugin for GamepadPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VirtualCursor>().add_systems(
+            Update,
+            (
+                (move_virtual_cursor, pan_camera_with_stick.run_if(camera_controller_enabled)).before(update_input_state),
+                draw_virtual_cursor.after(update_input_state),
+            ),
+        );
+    }
+}
+
+/// Cursor moved by the left stick, used instead of the mouse cursor until the mouse moves again.
+#[derive(Default, Resource)]
+pub struct VirtualCursor {
+    /// Position in logical window pixels, like [`Window::cursor_position`]
+    pub position: Option<Vec2>,
+    pub active: bool,
+}
+
+fn stick(axes: &Axis<GamepadAxis>, gamepads: &Gamepads, x: GamepadAxisType, y: GamepadAxisType) -> Vec2 {
+    gamepads
+        .iter()
+        .map(|gamepad| {
+            Vec2::new(
+                axes.get(GamepadAxis::new(gamepad, x)).unwrap_or(0.0),
+                axes.get(GamepadAxis::new(gamepad, y)).unwrap_or(0.0),
+            )
+        })
+        .find(|value| value.length() > STICK_DEADZONE)
+        .unwrap_or(Vec2::ZERO)
+}
+
+fn move_virtual_cursor(
+    time: Res<Time>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
+    mut cursor_moved_events: EventReader<CursorMoved>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut cursor: ResMut<VirtualCursor>,
+) {
+    let Ok(window) = window_query.get_single() else { return };
+
+    if cursor_moved_events.read().last().is_some() && cursor.active {
+        cursor.active = false;
+    }
+
+    let movement = stick(&axes, &gamepads, GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY);
+    if movement == Vec2::ZERO {
+        return;
+    }
+
+    let size = Vec2::new(window.width(), window.height());
+    let start = match (cursor.active, cursor.position) {
+        (true, Some(position)) => position,
+        // pick up where the mouse was, or the middle of the window
+        _ => window.cursor_position().unwrap_or(size / 2.0),
+    };
+    // window coordinates grow downwards, the stick grows upwards
+    let position = start + movement * Vec2::new(1.0, -1.0) * CURSOR_SPEED * time.delta_seconds();
+    cursor.position = Some(position.clamp(Vec2::ZERO, size));
+    cursor.active = true;
+}
+
+fn pan_camera_with_stick(
+    time: Res<Time>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
+    mut query: Query<(&mut Transform, &OrthographicProjection), With<PixelWorldCamera>>,
+) {
+    let pan = stick(&axes, &gamepads, GamepadAxisType::RightStickX, GamepadAxisType::RightStickY);
+    if pan == Vec2::ZERO {
+        return;
+    }
+    for (mut transform, ortho) in &mut query {
+        transform.translation += (pan * CAMERA_MOVE_SPEED * ortho.scale * time.delta_seconds()).extend(0.0);
+    }
+}
+
+// the OS cursor stays wherever the mouse left it, so show where the stick points
+fn draw_virtual_cursor(
+    mut gizmos: Gizmos,
+    cursor: Res<VirtualCursor>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<PixelWorldCamera>>,
+) {
+    let (true, Some(position)) = (cursor.active, cursor.position) else { return };
+    let Ok((camera, camera_transform)) = camera_q.get_single() else { return };
+    let Some(world_position) = camera.viewport_to_world_2d(camera_transform, position) else { return };
+
+    gizmos.line_2d(world_position - Vec2::X * CURSOR_SIZE, world_position + Vec2::X * CURSOR_SIZE, CURSOR_COLOR);
+    gizmos.line_2d(world_position - Vec2::Y * CURSOR_SIZE, world_position + Vec2::Y * CURSOR_SIZE, CURSOR_COLOR);
+}
@@ -0,0 +1,135 @@
+use bevy::math::{IVec2, URect, UVec2};
+
+use crate::CurrentElement;
+
+/// A rectangular block of cells, row by row starting at the top left like the world texture.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CellGrid {
+    pub size: UVec2,
+    pub cells: Vec<CurrentElement>,
+}
+
+impl CellGrid {
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            cells: vec![CurrentElement::AIR; (size.x * size.y) as usize],
+        }
+    }
+
+    /// Reads a grid from tightly packed RGBA8 pixels, mapping each one to the closest element.
+    pub fn from_rgba(size: UVec2, data: &[u8]) -> Self {
+        let cells = data
+            .chunks_exact(4)
+            .map(|pixel| CurrentElement::from_rgba([pixel[0], pixel[1], pixel[2], pixel[3]]))
+            .collect();
+        Self { size, cells }
+    }
+
+    /// Reads a grid drawn with [`CurrentElement::symbol`]s, one row per line.
+    /// Surrounding blank lines and indentation are ignored, every row must have the same width.
+    pub fn from_ascii(art: &str) -> Result<Self, String> {
+        let rows: Vec<&str> = art.lines().map(str::trim).skip_while(|row| row.is_empty()).collect();
+        let rows = &rows[..rows.iter().rposition(|row| !row.is_empty()).map_or(0, |last| last + 1)];
+
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        let mut grid = CellGrid::new(UVec2::new(width as u32, rows.len() as u32));
+        for (y, row) in rows.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(format!("row {y} is {} cells wide, expected {width}", row.chars().count()));
+            }
+            for (x, symbol) in row.chars().enumerate() {
+                let element = CurrentElement::from_symbol(symbol)
+                    .ok_or_else(|| format!("unknown cell '{symbol}' in row {y}"))?;
+                grid.set(x as u32, y as u32, element);
+            }
+        }
+        Ok(grid)
+    }
+
+    /// The grid drawn with [`CurrentElement::symbol`]s, one line per row.
+    pub fn to_ascii(&self) -> String {
+        let mut art = String::new();
+        for y in 0..self.size.y {
+            art.extend((0..self.size.x).map(|x| self.get(x, y).symbol()));
+            art.push('\n');
+        }
+        art
+    }
+
+    /// Cells holding `element`.
+    pub fn count(&self, element: CurrentElement) -> usize {
+        self.cells.iter().filter(|cell| **cell == element).count()
+    }
+
+    /// Tightly packed RGBA8 pixels in the colors the world texture stores the cells as.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        self.cells.iter().flat_map(|cell| cell.rgba()).collect()
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> CurrentElement {
+        self.cells[(y * self.size.x + x) as usize]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, element: CurrentElement) {
+        self.cells[(y * self.size.x + x) as usize] = element;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size.x == 0 || self.size.y == 0
+    }
+
+    /// The grid turned a quarter turn clockwise.
+    pub fn rotated(&self) -> Self {
+        let mut rotated = CellGrid::new(UVec2::new(self.size.y, self.size.x));
+        for y in 0..self.size.y {
+            for x in 0..self.size.x {
+                rotated.set(self.size.y - 1 - y, x, self.get(x, y));
+            }
+        }
+        rotated
+    }
+
+    /// The grid mirrored left to right.
+    pub fn flipped(&self) -> Self {
+        let mut flipped = CellGrid::new(self.size);
+        for y in 0..self.size.y {
+            for x in 0..self.size.x {
+                flipped.set(self.size.x - 1 - x, y, self.get(x, y));
+            }
+        }
+        flipped
+    }
+
+    /// The grid cut down or padded with air to `size`, keeping the top left corner in place.
+    pub fn resized(&self, size: UVec2) -> Self {
+        let mut resized = CellGrid::new(size);
+        let common = self.size.min(size);
+        for y in 0..common.y {
+            for x in 0..common.x {
+                resized.set(x, y, self.get(x, y));
+            }
+        }
+        resized
+    }
+
+    /// Cuts the grid down to the part that lands inside `bounds` when placed at `origin`,
+    /// returning where that part starts.
+    pub fn clipped(&self, origin: IVec2, bounds: URect) -> Option<(UVec2, CellGrid)> {
+        let min = origin.max(bounds.min.as_ivec2());
+        let max = (origin + self.size.as_ivec2()).min(bounds.max.as_ivec2());
+        if min.x >= max.x || min.y >= max.y {
+            return None;
+        }
+
+        let offset = (min - origin).as_uvec2();
+        let size = (max - min).as_uvec2();
+        let mut clipped = CellGrid::new(size);
+        for y in 0..size.y {
+            for x in 0..size.x {
+                clipped.set(x, y, self.get(offset.x + x, offset.y + y));
+            }
+        }
+        Some((min.as_uvec2(), clipped))
+    }
+}
@@ -1,8 +1,9 @@
 use bevy::{
     prelude::Image,
-    render::
-        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages}
-    ,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        texture::ImageSampler,
+    },
 };
 
 pub fn create_image(width: u32, height: u32) -> Image {
@@ -17,8 +18,12 @@ pub fn create_image(width: u32, height: u32) -> Image {
         TextureFormat::Rgba8Unorm,
     );
 
-    image.texture_descriptor.usage =
-        TextureUsages::COPY_DST | TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING;
+    image.texture_descriptor.usage = TextureUsages::COPY_DST
+        | TextureUsages::COPY_SRC
+        | TextureUsages::STORAGE_BINDING
+        | TextureUsages::TEXTURE_BINDING;
+    // every cell stays a crisp square at any zoom
+    image.sampler = ImageSampler::nearest();
 
     image
 }
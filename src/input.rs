@@ -1,11 +1,23 @@
 use bevy::{
-    input::{mouse::MouseButtonInput, ButtonState},
     prelude::*,
     render::extract_resource::ExtractResource,
 };
 
-use crate::CurrentElement;
+use crate::{actions::{Action, ActionState}, gamepad::VirtualCursor, brush::{BrushMaterial, BrushMode}, tools::{DrawShape, DrawTool}, CurrentElement, PixelWorld, PixelWorldCamera, PixelWorldConfig, PrimaryPixelWorld};
 
+const DEFAULT_BRUSH_RADIUS: f32 = 10.0;
+const MIN_BRUSH_RADIUS: f32 = 1.0;
+const MAX_BRUSH_RADIUS: f32 = 64.0;
+/// Factor one brush size step grows or shrinks the radius by.
+const BRUSH_RADIUS_STEP: f32 = 1.25;
+/// Most cells a single flood fill may change.
+const DEFAULT_FILL_BUDGET: u32 = 100_000;
+
+
+/// Run condition for the hotkeys and panels of plugins that are added either way, like the stats HUD.
+pub fn input_enabled(config: Res<PixelWorldConfig>) -> bool {
+    config.input
+}
 
 pub struct InputPlugin;
 impl Plugin for InputPlugin {
@@ -15,69 +27,160 @@ impl Plugin for InputPlugin {
     }
 }
 
-#[derive(Default, Resource, ExtractResource, Clone)]
+/// A brush stroke from `start` to `end` in canvas pixels, painted like the mouse brush.
+#[derive(Clone, Copy, Debug)]
+pub struct StrokeSegment {
+    pub start: Vec2,
+    pub end: Vec2,
+}
+
+#[derive(Resource, ExtractResource, Clone)]
 pub struct DrawingParams {
     pub mouse_pos: Vec2,
     pub is_drawing: bool,
     pub prev_mouse_pos: Vec2,
+    /// Paints air with the brush, whatever the selected tool.
     pub is_erasing: bool,
-    pub element: CurrentElement
+    /// The simulation is frozen, drawing still works.
+    pub paused: bool,
+    /// Advance a paused simulation by one tick this frame.
+    pub step: bool,
+    pub element: CurrentElement,
+    pub material: BrushMaterial,
+    pub mode: BrushMode,
+    pub brush_radius: f32,
+    pub tool: DrawTool,
+    /// Draw rectangles and ellipses as outlines instead of filled shapes.
+    pub hollow: bool,
+    /// Shapes committed this frame, stamped into the world by the draw pass.
+    pub shapes: Vec<DrawShape>,
+    /// Extra brush segments drawn this frame, one per finger on touch screens.
+    pub strokes: Vec<StrokeSegment>,
+    /// Cell a flood fill was started at this frame.
+    pub fill_seed: Option<Vec2>,
+    pub fill_budget: u32,
+    /// The cursor is over a UI element, clicks there must not reach the world.
+    pub pointer_over_ui: bool,
+    /// The cursor is inside the window and over a cell of the world.
+    pub cursor_in_world: bool,
+}
+
+impl Default for DrawingParams {
+    fn default() -> Self {
+        Self {
+            mouse_pos: Vec2::ZERO,
+            is_drawing: false,
+            prev_mouse_pos: Vec2::ZERO,
+            is_erasing: false,
+            paused: false,
+            step: false,
+            element: CurrentElement::default(),
+            material: BrushMaterial::default(),
+            mode: BrushMode::default(),
+            brush_radius: DEFAULT_BRUSH_RADIUS,
+            tool: DrawTool::default(),
+            hollow: false,
+            shapes: Vec::new(),
+            strokes: Vec::new(),
+            fill_seed: None,
+            fill_budget: DEFAULT_FILL_BUDGET,
+            pointer_over_ui: false,
+            cursor_in_world: false,
+        }
+    }
 }
 
 pub fn update_input_state(
     window_query: Query<&Window>,
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: Res<ActionState>,
+    virtual_cursor: Res<VirtualCursor>,
     mut input_state: ResMut<DrawingParams>,
-    camera_q: Query<(&Camera, &GlobalTransform)>,
-    mut mouse_button_input_events: EventReader<MouseButtonInput>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<PixelWorldCamera>>,
+    interactions: Query<&Interaction>,
+    primary: Query<(&PixelWorld, &Transform), With<PrimaryPixelWorld>>,
 ) {
-    let air = keyboard_input.pressed(KeyCode::E);
-    let sand = keyboard_input.pressed(KeyCode::R);
-    let water = keyboard_input.pressed(KeyCode::T);
-    let rock = keyboard_input.pressed(KeyCode::Y);
+    // shapes, strokes, fills and steps only live for the frame they were committed in
+    if !input_state.shapes.is_empty() {
+        input_state.shapes.clear();
+    }
+    if !input_state.strokes.is_empty() {
+        input_state.strokes.clear();
+    }
+    if input_state.fill_seed.is_some() {
+        input_state.fill_seed = None;
+    }
+    if input_state.step {
+        input_state.step = false;
+    }
+
+    if actions.just_pressed(Action::Pause) {
+        input_state.paused = !input_state.paused;
+    }
+    if actions.just_pressed(Action::Step) {
+        input_state.step = true;
+    }
+    if actions.just_pressed(Action::BrushSizeUp) {
+        input_state.brush_radius = (input_state.brush_radius * BRUSH_RADIUS_STEP).min(MAX_BRUSH_RADIUS);
+    }
+    if actions.just_pressed(Action::BrushSizeDown) {
+        input_state.brush_radius = (input_state.brush_radius / BRUSH_RADIUS_STEP).max(MIN_BRUSH_RADIUS);
+    }
 
     let Ok(primary_window) = window_query.get_single() else { return };
     // get the camera info and transform
     let Ok((camera, camera_transform)) = camera_q.get_single() else { return };
+    let Ok((world, world_transform)) = primary.get_single() else { return };
 
-    // Determine button state
-    for event in mouse_button_input_events.read() {
-        if event.button == MouseButton::Left {
-            input_state.is_drawing = event.state == ButtonState::Pressed;
-        }else if event.button == MouseButton::Right{
-            input_state.is_erasing = event.state == ButtonState::Pressed;
-        }
+    let over_ui = interactions.iter().any(|interaction| *interaction != Interaction::None);
+    if input_state.pointer_over_ui != over_ui {
+        input_state.pointer_over_ui = over_ui;
+    }
+
+    // strokes only start off the UI, but keep going when dragged over it
+    if actions.just_pressed(Action::Draw) {
+        input_state.is_drawing = !over_ui;
+    } else if !actions.pressed(Action::Draw) && input_state.is_drawing {
+        input_state.is_drawing = false;
+    }
+    if actions.just_pressed(Action::Erase) {
+        input_state.is_erasing = !over_ui;
+    } else if !actions.pressed(Action::Erase) && input_state.is_erasing {
+        input_state.is_erasing = false;
     }
-    
-    if !(air || sand || water || rock){
 
-    }else{
-        if air{
-            input_state.element = CurrentElement::AIR;
-        }else if sand{
-            input_state.element = CurrentElement::SAND;
-        }else if water{
-            input_state.element = CurrentElement::WATER;
-        }else if rock{
-            input_state.element = CurrentElement::ROCK;
-        };
+    if actions.just_pressed(Action::NextElement) {
+        input_state.element = input_state.element.cycled(1);
+    }
+    if actions.just_pressed(Action::PreviousElement) {
+        input_state.element = input_state.element.cycled(-1);
+    }
+    for element in CurrentElement::ALL {
+        if actions.pressed(Action::SelectElement(element)) && input_state.element != element {
+            input_state.element = element;
+        }
+        if actions.pressed(Action::FilterElement(element)) {
+            let mode = input_state.mode.with_filter(element);
+            if input_state.mode != mode {
+                input_state.mode = mode;
+            }
+        }
     }
 
-    if let Some(world_position) = primary_window
-        .cursor_position()
+    let cursor = if virtual_cursor.active {
+        virtual_cursor.position
+    } else {
+        primary_window.cursor_position()
+    };
+    let canvas_position = cursor
         .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
-        .map(|ray| ray.origin.truncate())
-    {
+        .map(|ray| world.world_to_canvas(world_transform, ray.origin.truncate()));
+    if let Some(canvas_position) = canvas_position {
         input_state.prev_mouse_pos = input_state.mouse_pos;
-        input_state.mouse_pos =
-            world_pos_to_canvas_pos(world_position * Vec2::new(1.0, -1.0));
+        input_state.mouse_pos = canvas_position;
     }
-}
 
-fn world_pos_to_canvas_pos(world_pos: Vec2) -> Vec2 {
-    world_pos
-        + Vec2::new(
-            crate::SIM_SIZE.0 as f32 / 2.0,
-            crate::SIM_SIZE.1 as f32 / 2.0,
-        )
-}
\ No newline at end of file
+    let cursor_in_world = canvas_position.is_some_and(|position| world.contains(position));
+    if input_state.cursor_in_world != cursor_in_world {
+        input_state.cursor_in_world = cursor_in_world;
+    }
+}
@@ -0,0 +1,188 @@
+use bevy::prelude::*;
+
+use crate::{
+    actions::{Action, ActionState},
+    grid::CellGrid,
+    input::{update_input_state, DrawingParams},
+    pipeline::transfer::{ReadbackFailed, ReadbackId, ReadbackRequests, RegionReadback},
+    PixelWorld, PixelWorldCamera, PrimaryPixelWorld,
+};
+
+const TOOLTIP_COLOR: Color = Color::rgba(0.1, 0.1, 0.1, 0.85);
+/// Offset of the tooltip from the cursor, in logical pixels.
+const TOOLTIP_OFFSET: Vec2 = Vec2::new(16.0, 16.0);
+
+/// Tooltip (toggled with I) showing the cell under the cursor, read back from the GPU.
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Inspector>()
+            .add_systems(Startup, spawn_tooltip)
+            .add_systems(
+                Update,
+                (toggle_inspector, request_hovered_cells, receive_hovered_cells, update_tooltip)
+                    .chain()
+                    .after(update_input_state),
+            );
+    }
+}
+
+#[derive(Default, Resource)]
+pub struct Inspector {
+    pub enabled: bool,
+    pending: Option<ReadbackId>,
+    /// Last readback of the hovered cell and its neighbours
+    hovered: Option<HoveredCells>,
+}
+
+struct HoveredCells {
+    /// Top left cell of `grid` in canvas cells
+    origin: UVec2,
+    grid: CellGrid,
+    /// Raw RGBA8 colors of `grid`, to spot colors that match no element
+    data: Vec<u8>,
+}
+
+#[derive(Component)]
+struct InspectorTooltip;
+
+fn spawn_tooltip(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    padding: UiRect::all(Val::Px(5.)),
+                    display: Display::None,
+                    ..default()
+                },
+                background_color: TOOLTIP_COLOR.into(),
+                // above the palette and panels
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            InspectorTooltip,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "",
+                TextStyle {
+                    font_size: 14.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn toggle_inspector(actions: Res<ActionState>, mut inspector: ResMut<Inspector>) {
+    if actions.just_pressed(Action::ToggleInspector) {
+        inspector.enabled = !inspector.enabled;
+        inspector.pending = None;
+        inspector.hovered = None;
+    }
+}
+
+// one readback in flight at a time, the tooltip lags the cursor by the few frames it takes
+fn request_hovered_cells(
+    params: Res<DrawingParams>,
+    mut inspector: ResMut<Inspector>,
+    mut readbacks: ResMut<ReadbackRequests>,
+    primary: Query<Entity, With<PrimaryPixelWorld>>,
+) {
+    if !inspector.enabled || !params.cursor_in_world || inspector.pending.is_some() {
+        return;
+    }
+    let Ok(world) = primary.get_single() else { return };
+    let cell = params.mouse_pos.floor().as_uvec2();
+    let region = URect::from_corners(cell.saturating_sub(UVec2::ONE), cell + UVec2::splat(2));
+    inspector.pending = Some(readbacks.request(world, region));
+}
+
+fn receive_hovered_cells(
+    mut readbacks: EventReader<RegionReadback>,
+    mut failed: EventReader<ReadbackFailed>,
+    mut inspector: ResMut<Inspector>,
+) {
+    // ask again next frame
+    if failed.read().any(|failed| inspector.pending == Some(failed.id)) {
+        inspector.pending = None;
+    }
+    for readback in readbacks.read() {
+        if inspector.pending != Some(readback.id) {
+            continue;
+        }
+        inspector.pending = None;
+        inspector.hovered = Some(HoveredCells {
+            origin: readback.region.min,
+            grid: readback.to_grid(),
+            data: readback.data.clone(),
+        });
+    }
+}
+
+fn update_tooltip(
+    params: Res<DrawingParams>,
+    inspector: Res<Inspector>,
+    primary: Query<(&PixelWorld, &Transform), With<PrimaryPixelWorld>>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<PixelWorldCamera>>,
+    mut tooltip: Query<(&mut Style, &Children), With<InspectorTooltip>>,
+    mut texts: Query<&mut Text>,
+) {
+    let Ok((mut style, children)) = tooltip.get_single_mut() else { return };
+    let Ok((camera, camera_transform)) = camera_q.get_single() else { return };
+    let Ok((world, world_transform)) = primary.get_single() else { return };
+
+    let screen_pos = camera.world_to_viewport(
+        camera_transform,
+        world.canvas_to_world(world_transform, params.mouse_pos).extend(0.0),
+    );
+    let visible = inspector.enabled && params.cursor_in_world && !params.pointer_over_ui;
+    let (Some(hovered), Some(screen_pos), true) = (&inspector.hovered, screen_pos, visible) else {
+        if style.display != Display::None {
+            style.display = Display::None;
+        }
+        return;
+    };
+
+    style.display = Display::Flex;
+    style.left = Val::Px(screen_pos.x + TOOLTIP_OFFSET.x);
+    style.top = Val::Px(screen_pos.y + TOOLTIP_OFFSET.y);
+
+    // the cursor may have moved on since the readback, fall back to the cell it was read for
+    let cell = params.mouse_pos.floor().as_uvec2();
+    let local = cell.saturating_sub(hovered.origin).min(hovered.grid.size - UVec2::ONE);
+    let cell = hovered.origin + local;
+    let element = hovered.grid.get(local.x, local.y);
+    let world_pos = world.canvas_to_world(world_transform, cell.as_vec2() + Vec2::splat(0.5));
+    let offset = ((local.y * hovered.grid.size.x + local.x) * 4) as usize;
+    let [r, g, b] = [0, 1, 2].map(|channel| hovered.data.get(offset + channel).copied().unwrap_or(0));
+
+    let mut neighbours = String::new();
+    for y in 0..hovered.grid.size.y {
+        for x in 0..hovered.grid.size.x {
+            neighbours.push(hovered.grid.get(x, y).symbol());
+            neighbours.push(' ');
+        }
+        neighbours.push('\n');
+    }
+
+    // the world texture only stores one color per cell, so there is no temperature, lifetime or velocity to show
+    let text = format!(
+        "{}\ncell ({}, {})\nworld ({:.0}, {:.0})\ncolor #{r:02x}{g:02x}{b:02x}\nneighbours\n{}",
+        element.name(),
+        cell.x,
+        cell.y,
+        world_pos.x,
+        world_pos.y,
+        neighbours.trim_end(),
+    );
+    for child in children.iter() {
+        if let Ok(mut label) = texts.get_mut(*child) {
+            if label.sections[0].value != text {
+                label.sections[0].value = text.clone();
+            }
+        }
+    }
+}
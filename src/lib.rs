@@ -1,33 +1,54 @@
 #![allow(clippy::type_complexity)]
 
 mod actions;
-mod audio;
+mod brush;
+#[cfg(not(target_arch = "wasm32"))]
+mod capture;
+mod clipboard;
+pub mod commands;
+pub mod config;
+pub mod cpu;
+mod debug_view;
+mod gamepad;
+pub mod grid;
 mod images;
 mod loading;
 mod menu;
-mod player;
+mod palette;
 mod pipeline;
+pub mod query;
+pub mod scenario;
+pub mod scene;
+pub mod shader_contract;
+mod stamps;
+mod stats;
 mod camera;
 mod input;
+mod inspector;
+mod tools;
+mod touch;
+pub mod triggers;
+pub mod worlds;
+
+pub use crate::commands::PixelWorldCommands;
+pub use crate::config::{PixelWorldCamera, PixelWorldConfig};
+pub use crate::query::PixelWorldQuery;
+pub use crate::worlds::{PixelWorld, PixelWorldClock, PixelWorldRules, PrimaryPixelWorld};
 
 use crate::actions::ActionsPlugin;
-use crate::audio::InternalAudioPlugin;
 use crate::loading::LoadingPlugin;
 use crate::menu::MenuPlugin;
-use crate::player::PlayerPlugin;
 
-use bevy::{app::App, render::{RenderApp, RenderSet, extract_resource::ExtractResourcePlugin, render_graph::RenderGraph, Render, camera::CameraPlugin}};
+use bevy::{app::App, render::extract_resource::ExtractResourcePlugin};
 #[cfg(debug_assertions)]
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use bevy::prelude::*;
 use input::DrawingParams;
-use pipeline::{automata::{GameOfLifeImage, prepare_bind_group}, PipelinesPlugin};
-// use pipeline::{GameOfLifePipeline, GameOfLifeNode, prepare_bind_group};
+use pipeline::PipelinesPlugin;
 
-const SIM_SIZE: (u32, u32) = (1280, 720);
 const WORKGROUP_SIZE: u32 = 8;
 
-#[derive(Clone, Default, PartialEq)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
 pub enum CurrentElement{
     #[default] AIR,
     SAND,
@@ -35,6 +56,96 @@ pub enum CurrentElement{
     ROCK
 }
 
+impl CurrentElement {
+    pub const ALL: [CurrentElement; 4] = [
+        CurrentElement::AIR,
+        CurrentElement::SAND,
+        CurrentElement::WATER,
+        CurrentElement::ROCK,
+    ];
+
+    /// Index of the element as understood by the shaders.
+    pub fn index(&self) -> u32 {
+        match self {
+            CurrentElement::AIR => 0,
+            CurrentElement::SAND => 1,
+            CurrentElement::WATER => 2,
+            CurrentElement::ROCK => 3,
+        }
+    }
+
+    /// Color the element is stored as in the world texture.
+    pub fn color(&self) -> Vec4 {
+        match self {
+            CurrentElement::AIR => pipeline::draw::AIR,
+            CurrentElement::SAND => pipeline::draw::SAND,
+            CurrentElement::WATER => pipeline::draw::WATER,
+            CurrentElement::ROCK => pipeline::draw::ROCK,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CurrentElement::AIR => "Air",
+            CurrentElement::SAND => "Sand",
+            CurrentElement::WATER => "Water",
+            CurrentElement::ROCK => "Rock",
+        }
+    }
+
+    /// Single character standing for the element in text grids.
+    pub fn symbol(&self) -> char {
+        match self {
+            CurrentElement::AIR => '.',
+            CurrentElement::SAND => 's',
+            CurrentElement::WATER => 'w',
+            CurrentElement::ROCK => '#',
+        }
+    }
+
+    /// Element standing for `symbol` in text grids, see [`CurrentElement::symbol`].
+    pub fn from_symbol(symbol: char) -> Option<CurrentElement> {
+        CurrentElement::ALL.into_iter().find(|element| element.symbol() == symbol)
+    }
+
+    /// Whether the element blocks raycasts and counts as ground, see [`query::PixelWorldQuery`].
+    pub fn is_solid(&self) -> bool {
+        matches!(self, CurrentElement::SAND | CurrentElement::ROCK)
+    }
+
+    /// Default key bound to selecting the element.
+    pub fn hotkey(&self) -> KeyCode {
+        match self {
+            CurrentElement::AIR => KeyCode::E,
+            CurrentElement::SAND => KeyCode::R,
+            CurrentElement::WATER => KeyCode::T,
+            CurrentElement::ROCK => KeyCode::Y,
+        }
+    }
+
+    /// Element `offset` places further along [`CurrentElement::ALL`], wrapping around.
+    pub fn cycled(&self, offset: i32) -> CurrentElement {
+        let count = CurrentElement::ALL.len() as i32;
+        CurrentElement::ALL[(self.index() as i32 + offset).rem_euclid(count) as usize]
+    }
+
+    /// Color as stored in the `Rgba8Unorm` world texture.
+    pub fn rgba(&self) -> [u8; 4] {
+        (self.color() * 255.0).round().as_uvec4().to_array().map(|channel| channel as u8)
+    }
+
+    /// Element whose color is closest to `rgba`.
+    pub fn from_rgba(rgba: [u8; 4]) -> CurrentElement {
+        let color = Vec4::from_array(rgba.map(|channel| channel as f32 / 255.0));
+        CurrentElement::ALL
+            .into_iter()
+            .min_by(|a, b| {
+                a.color().distance_squared(color).total_cmp(&b.color().distance_squared(color))
+            })
+            .unwrap()
+    }
+}
+
 // This example game uses States to separate logic
 // See https://bevy-cheatbook.github.io/programming/states.html
 // Or https://github.com/bevyengine/bevy/blob/main/examples/ecs/state.rs
@@ -49,46 +160,113 @@ enum GameState {
     Menu,
 }
 
-pub struct PixelWorldPlugin;
+/// Runs the simulation, set up by its [`PixelWorldConfig`], and spawns the [`PrimaryPixelWorld`].
+pub struct PixelWorldPlugin {
+    pub config: PixelWorldConfig,
+    /// Cells of the primary world, both sides multiples of 8
+    pub size: UVec2,
+    /// Placement of the primary world sprite and its overlays
+    pub transform: Transform,
+    pub rules: PixelWorldRules,
+}
+
+impl Default for PixelWorldPlugin {
+    fn default() -> Self {
+        Self {
+            config: PixelWorldConfig::default(),
+            size: UVec2::new(1280, 720),
+            transform: Transform::IDENTITY,
+            rules: PixelWorldRules::default(),
+        }
+    }
+}
 
 impl Plugin for PixelWorldPlugin {
     fn build(&self, app: &mut App) {
-       // Extract the game of life image resource from the main world into the render world
-        // for operation on by the compute shader and display on the sprite.
+        let config = &self.config;
+        assert!(
+            self.size.cmpgt(UVec2::ZERO).all() && (self.size % WORKGROUP_SIZE).cmpeq(UVec2::ZERO).all(),
+            "the world size {} must be a non-zero multiple of {WORKGROUP_SIZE} cells on both sides",
+            self.size
+        );
+        app.insert_resource(config.clone());
+
         app
-        .add_systems(Startup, setup)
-        .add_plugins(ExtractResourcePlugin::<GameOfLifeImage>::default())
+        .add_systems(Startup, setup(self.size, self.transform, self.rules))
         .add_plugins(ExtractResourcePlugin::<DrawingParams>::default())
-        .add_plugins(camera::CameraPlugin)
-        .add_plugins(input::InputPlugin)
+        .add_plugins(worlds::PixelWorldsPlugin)
+        .add_plugins(pipeline::transfer::TransferPlugin)
+        .add_plugins(commands::WorldCommandsPlugin)
+        .add_plugins(query::WorldQueryPlugin)
+        .add_plugins(triggers::RegionTriggersPlugin)
+        .add_plugins(stats::StatsPlugin)
+        .add_plugins(debug_view::DebugViewPlugin)
         .add_plugins(PipelinesPlugin);
-        
+
+        // the host game's keys and buttons are only read when the world is controlled through them
+        if config.input || config.camera_controller {
+            app.add_plugins(ActionsPlugin);
+        } else {
+            app.init_resource::<actions::ActionState>();
+        }
+        if config.camera_controller {
+            app.add_plugins(camera::CameraPlugin);
+        }
+        if config.input {
+            app.add_plugins((
+                input::InputPlugin,
+                gamepad::GamepadPlugin,
+                touch::TouchPlugin,
+                tools::ToolsPlugin,
+                clipboard::ClipboardPlugin,
+                stamps::StampsPlugin,
+                palette::PalettePlugin,
+                inspector::InspectorPlugin,
+                actions::RebindPlugin,
+                scene::ScenePlugin,
+            ));
+            #[cfg(not(target_arch = "wasm32"))]
+            app.add_plugins(capture::CapturePlugin);
+        } else {
+            // still read by the passes and the camera controller
+            app.init_resource::<DrawingParams>().init_resource::<gamepad::VirtualCursor>();
+        }
+
+        // a host game may already log its own diagnostics
         #[cfg(debug_assertions)]
         {
-            app.add_plugins((FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin::default()));
+            if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+                app.add_plugins(FrameTimeDiagnosticsPlugin);
+            }
+            if !app.is_plugin_added::<LogDiagnosticsPlugin>() {
+                app.add_plugins(LogDiagnosticsPlugin::default());
+            }
         }
     }
-
-    // fn finish(&self, app: &mut App) {
-    //     let render_app = app.sub_app_mut(RenderApp);
-    //     // render_app.init_resource::<GameOfLifePipeline>();
-    // }
 }
 
 // add this in build, and first system on startup
-fn setup(mut commands: Commands, mut images_res: ResMut<Assets<Image>>) {
-    let image = images::create_image(SIM_SIZE.0, SIM_SIZE.1);
-    let image = images_res.add(image);
-
-    commands.spawn(SpriteBundle {
-        sprite: Sprite {
-            custom_size: Some(Vec2::new(SIM_SIZE.0 as f32, SIM_SIZE.1 as f32)),
-            ..default()
-        },
-        texture: image.clone(),
-        ..default()
-    });
-
-    commands.spawn(Camera2dBundle::default());
-    commands.insert_resource(pipeline::automata::GameOfLifeImage(image));
+fn setup(
+    size: UVec2,
+    transform: Transform,
+    rules: PixelWorldRules,
+) -> impl FnMut(Commands, ResMut<Assets<Image>>, ResMut<GizmoConfig>, Res<PixelWorldConfig>) {
+    move |mut commands, mut images_res, mut gizmo_config, config| {
+        let world = PixelWorld::new(size, &mut images_res).with_rules(rules);
+
+        commands.spawn((
+            world.sprite(transform),
+            world,
+            PrimaryPixelWorld,
+            triggers::RegionTriggers::default(),
+            config.render_layers,
+        ));
+
+        if config.input && config.gizmos_on_world_layers {
+            gizmo_config.render_layers = config.render_layers;
+        }
+        if config.spawn_camera {
+            commands.spawn((Camera2dBundle::default(), PixelWorldCamera, config.render_layers));
+        }
+    }
 }
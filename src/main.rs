@@ -12,9 +12,6 @@ use winit::window::Icon;
 
 mod images;
 
-const SIM_SIZE: (u32, u32) = (1280, 720);
-const WORKGROUP_SIZE: u32 = 8;
-
 fn main() {
     App::new()
     .insert_resource(Msaa::Off)
@@ -33,7 +30,7 @@ fn main() {
             ..default()
         }),
         ..default()
-    }), PixelWorldPlugin)
+    }), PixelWorldPlugin::default())
 )
     // .add_systems(Startup, setup)
     .add_systems(Startup, set_window_icon)
@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+
+use crate::{actions::{Action, ActionMap}, input::DrawingParams, CurrentElement};
+
+const BAR_COLOR: Color = Color::rgba(0.1, 0.1, 0.1, 0.85);
+const ENTRY_NORMAL: Color = Color::rgb(0.15, 0.15, 0.15);
+const ENTRY_HOVERED: Color = Color::rgb(0.25, 0.25, 0.25);
+const SELECTED_BORDER: Color = Color::rgb(0.9, 0.9, 0.9);
+
+/// A bar along the bottom of the screen with one clickable entry per element,
+/// highlighting the element currently drawn with.
+pub struct PalettePlugin;
+
+impl Plugin for PalettePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_palette)
+            .add_systems(Update, (click_palette_entry, highlight_selected_element, update_palette_labels).chain());
+    }
+}
+
+#[derive(Component)]
+struct PaletteEntry(CurrentElement);
+
+/// Text naming the element and the input it is bound to.
+#[derive(Component)]
+struct PaletteLabel(CurrentElement);
+
+fn spawn_palette(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.),
+                width: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            flex_wrap: FlexWrap::Wrap,
+                            justify_content: JustifyContent::Center,
+                            padding: UiRect::all(Val::Px(5.)),
+                            column_gap: Val::Px(5.),
+                            row_gap: Val::Px(5.),
+                            ..default()
+                        },
+                        background_color: BAR_COLOR.into(),
+                        ..default()
+                    },
+                    // blocks drawing while the cursor is over the bar
+                    Interaction::default(),
+                ))
+                .with_children(|parent| {
+                    for element in CurrentElement::ALL {
+                        spawn_entry(parent, element);
+                    }
+                });
+        });
+}
+
+fn spawn_entry(parent: &mut ChildBuilder, element: CurrentElement) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    height: Val::Px(34.0),
+                    align_items: AlignItems::Center,
+                    padding: UiRect::horizontal(Val::Px(6.)),
+                    column_gap: Val::Px(6.),
+                    border: UiRect::all(Val::Px(2.)),
+                    ..default()
+                },
+                background_color: ENTRY_NORMAL.into(),
+                border_color: Color::NONE.into(),
+                ..default()
+            },
+            PaletteEntry(element),
+        ))
+        .with_children(|parent| {
+            // the world texture stores linear colors, match how the sprite shows them
+            let [r, g, b, a] = element.color().to_array();
+            parent.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Px(20.),
+                    height: Val::Px(20.),
+                    ..default()
+                },
+                background_color: Color::rgba_linear(r, g, b, a).into(),
+                ..default()
+            });
+            parent.spawn((
+                TextBundle::from_section(
+                    element.name(),
+                    TextStyle {
+                        font_size: 16.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                ),
+                PaletteLabel(element),
+            ));
+        });
+}
+
+fn click_palette_entry(
+    mut params: ResMut<DrawingParams>,
+    mut interaction_query: Query<(&Interaction, &mut BackgroundColor, &PaletteEntry), Changed<Interaction>>,
+) {
+    for (interaction, mut color, entry) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                params.element = entry.0;
+            }
+            Interaction::Hovered => {
+                *color = ENTRY_HOVERED.into();
+            }
+            Interaction::None => {
+                *color = ENTRY_NORMAL.into();
+            }
+        }
+    }
+}
+
+fn highlight_selected_element(params: Res<DrawingParams>, mut entries: Query<(&PaletteEntry, &mut BorderColor)>) {
+    for (entry, mut border) in &mut entries {
+        let color = if entry.0 == params.element {
+            SELECTED_BORDER
+        } else {
+            Color::NONE
+        };
+        if border.0 != color {
+            border.0 = color;
+        }
+    }
+}
+
+fn update_palette_labels(map: Res<ActionMap>, mut labels: Query<(&PaletteLabel, &mut Text)>) {
+    if !map.is_changed() {
+        return;
+    }
+    for (label, mut text) in &mut labels {
+        let element = label.0;
+        text.sections[0].value = match map.label(Action::SelectElement(element)) {
+            Some(binding) => format!("{} ({binding})", element.name()),
+            None => element.name().to_string(),
+        };
+    }
+}
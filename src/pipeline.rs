@@ -1,5 +1,10 @@
 pub mod automata;
+pub mod debug;
 pub mod draw;
+pub mod fill;
+pub mod stats;
+pub mod transfer;
+pub mod triggers;
 
 use bevy::{prelude::*, render::{render_graph::RenderGraph, RenderApp}};
 
@@ -15,18 +20,32 @@ impl Plugin for PipelinesPlugin{
         let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
         let gol_id = render_graph.add_node("game_of_life", automata::AutomataNode::default());
         let draw_id = render_graph.add_node("game_of_life_draw", draw::AutomataDrawNode::default());
+        let fill_id = render_graph.add_node("game_of_life_fill", fill::FloodFillNode::default());
+        let readback_id = render_graph.add_node("game_of_life_readback", transfer::ReadbackNode);
+        let stats_id = render_graph.add_node("game_of_life_stats", stats::StatsNode::default());
+        let triggers_id = render_graph.add_node("game_of_life_triggers", triggers::RegionTriggersNode::default());
+        let debug_id = render_graph.add_node("game_of_life_debug", debug::DebugViewNode::default());
 
         /*
-         * Draw Pipeline => Automata Pipeline => Camera Driver
+         * Readback => Draw Pipeline => Flood Fill Pipeline => Automata Pipeline => Stats Pipeline => Region Triggers Pipeline => Debug View Pipeline => Camera Driver
          */
-        render_graph.add_node_edge(draw_id, gol_id);
-        render_graph.add_node_edge(gol_id, bevy::render::main_graph::node::CAMERA_DRIVER);
+        render_graph.add_node_edge(readback_id, draw_id);
+        render_graph.add_node_edge(draw_id, fill_id);
+        render_graph.add_node_edge(fill_id, gol_id);
+        render_graph.add_node_edge(gol_id, stats_id);
+        render_graph.add_node_edge(stats_id, triggers_id);
+        render_graph.add_node_edge(triggers_id, debug_id);
+        render_graph.add_node_edge(debug_id, bevy::render::main_graph::node::CAMERA_DRIVER);
     }
 
     fn finish(&self, app: &mut App) {
          let render_app = app.sub_app_mut(RenderApp);
         render_app
         .add_plugins(draw::DrawPipelinePlugin)
+        .add_plugins(fill::FillPipelinePlugin)
+        .add_plugins(stats::StatsPipelinePlugin)
+        .add_plugins(triggers::TriggersPipelinePlugin)
+        .add_plugins(debug::DebugPipelinePlugin)
         .add_plugins(automata::AutomataPipelinePlugin);
     }
 }
\ No newline at end of file
@@ -1,21 +1,31 @@
 use std::borrow::Cow;
 
-use bevy::{ecs::{system::{Resource, Commands, Res}, world::{FromWorld, World}, schedule::IntoSystemConfigs}, prelude::Deref, render::{extract_resource::ExtractResource, texture::Image, RenderSet, render_resource::{CachedComputePipelineId, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, ShaderStages, BindingType, TextureFormat, StorageTextureAccess, TextureViewDimension, PipelineCache, ComputePipelineDescriptor, BindGroup, BindGroupEntries, CachedPipelineState, ComputePassDescriptor}, renderer::{RenderDevice, RenderContext}, render_asset::RenderAssets, render_graph, RenderApp, Render}, asset::{Handle, AssetServer}, app::{Plugin, App, Startup}};
+use bevy::{ecs::{entity::Entity, query::QueryState, system::{Resource, Commands, Query, Res}, world::{FromWorld, World}, schedule::IntoSystemConfigs, component::Component}, asset::AssetId, utils::HashSet, render::{texture::Image, RenderSet, render_resource::{CachedComputePipelineId, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, ShaderStages, BindingType, TextureFormat, StorageTextureAccess, TextureViewDimension, PipelineCache, ComputePipelineDescriptor, BindGroup, BindGroupEntries, CachedPipelineState, ComputePassDescriptor, PushConstantRange}, renderer::{RenderDevice, RenderContext}, render_asset::RenderAssets, render_graph, Render}, asset::AssetServer, app::{Plugin, App}};
 
-use crate::{SIM_SIZE, WORKGROUP_SIZE, input::DrawingParams};
+use crate::{shader_contract::{push_constant_layout, PushConstantLayout}, PixelWorld};
 
 use super::draw::DrawPipeline;
 
-#[derive(Resource, Clone, Deref, ExtractResource, Debug)]
-pub struct GameOfLifeImage(pub Handle<Image>);
-
 pub struct AutomataPipelinePlugin;
 impl Plugin for AutomataPipelinePlugin {
     fn build(&self, render_app: &mut App) {
         render_app
             .init_resource::<AutomataPipeline>()
             .init_resource::<DrawPipeline>()
-            .add_systems(Render, prepare_bind_group.in_set(RenderSet::PrepareBindGroups));
+            .add_systems(Render, prepare_world_bind_groups.in_set(RenderSet::PrepareBindGroups));
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AutomataPushConstants {
+    rules: u32,
+}
+
+impl AutomataPushConstants {
+    /// Layout the `PushConstants` struct in game_of_life.wgsl must match.
+    pub(crate) fn layout() -> PushConstantLayout {
+        push_constant_layout!("shaders/game_of_life.wgsl", Self { rules })
     }
 }
 
@@ -65,7 +75,10 @@ impl FromWorld for AutomataPipeline {
             shader_defs: vec![],
             layout: vec![texture_bind_group_layout.clone()],
             entry_point: Cow::from("update"),
-            push_constant_ranges: Vec::new(),
+            push_constant_ranges: vec![PushConstantRange {
+                stages: ShaderStages::COMPUTE,
+                range: 0..std::mem::size_of::<AutomataPushConstants>() as u32,
+            }],
             label: Some(Cow::Borrowed("Game of Life Update Pipeline")),
         });
     
@@ -77,65 +90,60 @@ impl FromWorld for AutomataPipeline {
     }
 }
 
-#[derive(Resource)]
-pub struct AutomataImageBindGroup(pub BindGroup);
+/// Binds the texture of one [`PixelWorld`] for the automata pass.
+#[derive(Component)]
+pub struct PixelWorldBindGroup(pub BindGroup);
 
-pub fn prepare_bind_group(
+pub fn prepare_world_bind_groups(
     mut commands: Commands,
     render_device: Res<RenderDevice>,
     pipeline: Res<AutomataPipeline>,
     gpu_images: Res<RenderAssets<Image>>,
-    game_of_life_image: Res<GameOfLifeImage>,
+    worlds: Query<(Entity, &PixelWorld)>,
 ) {
-    // dbg!(&game_of_life_image);
-    let view = &gpu_images.get(&game_of_life_image.0).unwrap();
-
-    let bind_group = render_device.create_bind_group(
-         Some("Game of Life Bind Group"),
-         &pipeline.texture_bind_group_layout,
-         &BindGroupEntries::single(&view.texture_view),
-    );
-    commands.insert_resource(AutomataImageBindGroup(bind_group));
-}
-
-pub enum AutomataState{
-    Loading,
-    Init,
-    Update
+    for (entity, pixel_world) in &worlds {
+        // not uploaded yet
+        let Some(view) = gpu_images.get(&pixel_world.image) else { continue };
+        let bind_group = render_device.create_bind_group(
+            Some("Pixel World Bind Group"),
+            &pipeline.texture_bind_group_layout,
+            &BindGroupEntries::single(&view.texture_view),
+        );
+        commands.entity(entity).insert(PixelWorldBindGroup(bind_group));
+    }
 }
 
+/// Steps every [`PixelWorld`] as many ticks as its clock asks for by its own rules,
+/// filling it with air first on its first frame.
+#[derive(Default)]
 pub struct AutomataNode{
-    state: AutomataState
-}
-
-impl Default for AutomataNode{
-    fn default() -> Self {
-        Self { state: AutomataState::Loading }
-    }
+    ready: bool,
+    worlds: Option<QueryState<(Entity, &'static PixelWorld, &'static PixelWorldBindGroup)>>,
+    /// Textures already filled with air
+    initialized: HashSet<AssetId<Image>>,
+    /// Worlds to step this frame, and whether they need filling first
+    dispatches: Vec<(Entity, bool)>,
 }
 
 impl render_graph::Node for AutomataNode {
     fn update(&mut self, world: &mut World) {
-        let pipeline = world.resource::<AutomataPipeline>();
-        let pipeline_cache = world.resource::<PipelineCache>();
+        if !self.ready {
+            let pipeline = world.resource::<AutomataPipeline>();
+            let pipeline_cache = world.resource::<PipelineCache>();
+            self.ready = [pipeline.init_pipeline, pipeline.update_pipeline].into_iter().all(|id| {
+                matches!(pipeline_cache.get_compute_pipeline_state(id), CachedPipelineState::Ok(_))
+            });
+        }
 
-        // if the corresponding pipeline has loaded, transition to the next stage
-        match self.state {
-            AutomataState::Loading => {
-                if let CachedPipelineState::Ok(_) =
-                    pipeline_cache.get_compute_pipeline_state(pipeline.init_pipeline)
-                {
-                    self.state = AutomataState::Init;
-                }
-            }
-            AutomataState::Init => {
-                if let CachedPipelineState::Ok(_) =
-                    pipeline_cache.get_compute_pipeline_state(pipeline.update_pipeline)
-                {
-                    self.state = AutomataState::Update;
-                }
-            }
-            AutomataState::Update => {}
+        self.dispatches.clear();
+        let worlds = self.worlds.get_or_insert_with(|| QueryState::new(world));
+        worlds.update_archetypes(world);
+        if !self.ready {
+            return;
+        }
+        for (entity, pixel_world, _) in worlds.iter_manual(world) {
+            let init = self.initialized.insert(pixel_world.image.id());
+            self.dispatches.push((entity, init));
         }
     }
 
@@ -145,38 +153,33 @@ impl render_graph::Node for AutomataNode {
         render_context: &mut RenderContext,
         world: &World,
     ) -> Result<(), render_graph::NodeRunError> {
-        // dbg!(&world.resource::<AutomataImageBindGroup>().0);
-        let texture_bind_group = &world.resource::<AutomataImageBindGroup>().0;
+        let (true, Some(worlds)) = (self.ready, &self.worlds) else {
+            return Ok(());
+        };
         let pipeline_cache = world.resource::<PipelineCache>();
         let pipeline = world.resource::<AutomataPipeline>();
+        let init_pipeline = pipeline_cache.get_compute_pipeline(pipeline.init_pipeline).unwrap();
+        let update_pipeline = pipeline_cache.get_compute_pipeline(pipeline.update_pipeline).unwrap();
 
         let mut pass = render_context
             .command_encoder()
             .begin_compute_pass(&ComputePassDescriptor{label: Some("cpd-automata")});
 
-        pass.set_bind_group(0, texture_bind_group, &[]);
-        let params = &world.resource::<DrawingParams>();
-        // select the pipeline based on the current state
-        if !params.is_erasing{
-            match self.state {
-                AutomataState::Loading => {}
-                AutomataState::Init => {
-                    let init_pipeline = pipeline_cache
-                        .get_compute_pipeline(pipeline.init_pipeline)
-                        .unwrap();
-                    pass.set_pipeline(init_pipeline);
-                    pass.dispatch_workgroups(SIM_SIZE.0 / WORKGROUP_SIZE, SIM_SIZE.1 / WORKGROUP_SIZE, 1);
-                }
-                AutomataState::Update => {
-                    let update_pipeline = pipeline_cache
-                        .get_compute_pipeline(pipeline.update_pipeline)
-                        .unwrap();
-                    pass.set_pipeline(update_pipeline);
-                    pass.dispatch_workgroups(SIM_SIZE.0 / WORKGROUP_SIZE, SIM_SIZE.1 / WORKGROUP_SIZE, 1);
-                }
+        for (entity, init) in &self.dispatches {
+            let Ok((_, pixel_world, bind_group)) = worlds.get_manual(world, *entity) else { continue };
+            let workgroups = pixel_world.workgroups();
+            pass.set_bind_group(0, &bind_group.0, &[]);
+            if *init {
+                pass.set_pipeline(init_pipeline);
+                pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+            }
+            pass.set_pipeline(update_pipeline);
+            pass.set_push_constants(0, bytemuck::cast_slice(&[AutomataPushConstants { rules: pixel_world.rules.flags() }]));
+            for _ in 0..pixel_world.clock.ticks_due {
+                pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
             }
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,305 @@
+use std::borrow::Cow;
+
+use bevy::{
+    app::{App, Plugin},
+    asset::AssetServer,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::QueryState,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut, Resource},
+        world::{FromWorld, World},
+    },
+    render::{
+        render_asset::RenderAssets,
+        render_graph,
+        render_resource::{
+            BindGroup, BindGroupEntries, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+            BindingType, Buffer, BufferBindingType, BufferDescriptor, BufferUsages, CachedComputePipelineId,
+            CachedPipelineState, ComputePassDescriptor, ComputePipelineDescriptor, PipelineCache,
+            PushConstantRange, ShaderStages, StorageTextureAccess, TextureFormat, TextureViewDimension,
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::Image,
+        Render, RenderSet,
+    },
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    debug_view::{DebugOverlayImages, DebugView},
+    shader_contract::{push_constant_layout, PushConstantLayout},
+    PixelWorld,
+};
+
+/// Side of the square chunks the chunk activity view outlines, in cells.
+pub(crate) const CHUNK_SIZE: u32 = 32;
+
+pub struct DebugPipelinePlugin;
+
+impl Plugin for DebugPipelinePlugin {
+    fn build(&self, render_app: &mut App) {
+        render_app.init_resource::<DebugPipeline>().init_resource::<ChunkBuffers>().add_systems(
+            Render,
+            (
+                prepare_chunk_buffers.in_set(RenderSet::PrepareResources),
+                prepare_bind_group.in_set(RenderSet::PrepareBindGroups),
+            ),
+        );
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DebugPushConstants {
+    view: u32,
+    reset: u32,
+}
+
+impl DebugPushConstants {
+    /// Layout the `PushConstants` struct in debug.wgsl must match.
+    pub(crate) fn layout() -> PushConstantLayout {
+        push_constant_layout!("shaders/debug.wgsl", Self { view, reset })
+    }
+}
+
+#[derive(Resource)]
+pub struct DebugPipeline {
+    track_pipeline: CachedComputePipelineId,
+    draw_pipeline: CachedComputePipelineId,
+    debug_bind_group_layout: BindGroupLayout,
+}
+
+impl FromWorld for DebugPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let storage_texture_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::StorageTexture {
+                access: StorageTextureAccess::ReadWrite,
+                format: TextureFormat::Rgba8Unorm,
+                view_dimension: TextureViewDimension::D2,
+            },
+            count: None,
+        };
+        let debug_bind_group_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Debug View Bind Group Layout"),
+            entries: &[
+                storage_texture_entry(0),
+                storage_texture_entry(1),
+                storage_texture_entry(2),
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = world.resource::<AssetServer>().load("shaders/debug.wgsl");
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let push_constant_ranges = vec![PushConstantRange {
+            stages: ShaderStages::COMPUTE,
+            range: 0..std::mem::size_of::<DebugPushConstants>() as u32,
+        }];
+
+        let track_pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            shader: shader.clone(),
+            shader_defs: vec![],
+            entry_point: Cow::from("track"),
+            layout: vec![debug_bind_group_layout.clone()],
+            label: Some(Cow::Borrowed("Debug View Track Pipeline")),
+            push_constant_ranges: push_constant_ranges.clone(),
+        });
+        let draw_pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            shader,
+            shader_defs: vec![],
+            entry_point: Cow::from("draw"),
+            layout: vec![debug_bind_group_layout.clone()],
+            label: Some(Cow::Borrowed("Debug View Draw Pipeline")),
+            push_constant_ranges,
+        });
+
+        DebugPipeline {
+            track_pipeline,
+            draw_pipeline,
+            debug_bind_group_layout,
+        }
+    }
+}
+
+/// Chunk activity of every world, kept across frames unlike the render world entities.
+#[derive(Default, Resource)]
+struct ChunkBuffers(HashMap<Entity, Buffer>);
+
+/// Binds one [`PixelWorld`], its overlay textures and chunk activity for the debug pass.
+#[derive(Component)]
+pub struct DebugBindGroup {
+    bind_group: BindGroup,
+    chunk_buffer: Buffer,
+}
+
+fn prepare_chunk_buffers(
+    render_device: Res<RenderDevice>,
+    mut buffers: ResMut<ChunkBuffers>,
+    worlds: Query<(Entity, &PixelWorld)>,
+) {
+    buffers.0.retain(|entity, _| worlds.contains(*entity));
+    for (entity, pixel_world) in &worlds {
+        buffers.0.entry(entity).or_insert_with(|| {
+            let size = pixel_world.size;
+            let chunk_count = size.x.div_ceil(CHUNK_SIZE) * size.y.div_ceil(CHUNK_SIZE);
+            render_device.create_buffer(&BufferDescriptor {
+                label: Some("Debug View Chunk Buffer"),
+                size: chunk_count as u64 * std::mem::size_of::<u32>() as u64,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+    }
+}
+
+fn prepare_bind_group(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    pipeline: Res<DebugPipeline>,
+    buffers: Res<ChunkBuffers>,
+    gpu_images: Res<RenderAssets<Image>>,
+    worlds: Query<(Entity, &PixelWorld, &DebugOverlayImages)>,
+) {
+    for (entity, pixel_world, debug_images) in &worlds {
+        let (Some(view), Some(overlay), Some(previous), Some(chunk_buffer)) = (
+            gpu_images.get(&pixel_world.image),
+            gpu_images.get(&debug_images.overlay),
+            gpu_images.get(&debug_images.previous),
+            buffers.0.get(&entity),
+        ) else {
+            continue;
+        };
+        let bind_group = render_device.create_bind_group(
+            Some("Debug View Bind Group"),
+            &pipeline.debug_bind_group_layout,
+            &BindGroupEntries::sequential((
+                &view.texture_view,
+                &overlay.texture_view,
+                &previous.texture_view,
+                chunk_buffer.as_entire_binding(),
+            )),
+        );
+        commands.entity(entity).insert(DebugBindGroup {
+            bind_group,
+            chunk_buffer: chunk_buffer.clone(),
+        });
+    }
+}
+
+// ================================== Nodes ================================== //
+/// Draws the selected [`DebugView`] into the overlay texture of every world after the automata stepped.
+pub struct DebugViewNode {
+    ready: bool,
+    /// View drawn last frame, a different one starts from a clean overlay
+    last_view: DebugView,
+    worlds: Option<QueryState<(Entity, &'static PixelWorld, &'static DebugBindGroup)>>,
+    /// Worlds drawn last frame, any other world starts from a clean overlay
+    drawn: HashSet<Entity>,
+    /// Worlds to draw this frame, with their push constants
+    dispatches: Vec<(Entity, DebugPushConstants)>,
+}
+
+impl Default for DebugViewNode {
+    fn default() -> Self {
+        Self {
+            ready: false,
+            last_view: DebugView::Off,
+            worlds: None,
+            drawn: HashSet::new(),
+            dispatches: Vec::new(),
+        }
+    }
+}
+
+impl render_graph::Node for DebugViewNode {
+    fn update(&mut self, world: &mut World) {
+        if !self.ready {
+            let pipeline_cache = world.resource::<PipelineCache>();
+            let pipeline = world.resource::<DebugPipeline>();
+            self.ready = [pipeline.track_pipeline, pipeline.draw_pipeline].into_iter().all(|id| {
+                matches!(pipeline_cache.get_compute_pipeline_state(id), CachedPipelineState::Ok(_))
+            });
+        }
+
+        let view = *world.resource::<DebugView>();
+        let view_changed = view != self.last_view;
+        self.last_view = view;
+
+        let worlds = self.worlds.get_or_insert_with(|| QueryState::new(world));
+        worlds.update_archetypes(world);
+        self.dispatches.clear();
+        if !self.ready || view == DebugView::Off {
+            self.drawn.clear();
+            return;
+        }
+        let mut drawn = HashSet::new();
+        for (entity, _, _) in worlds.iter_manual(world) {
+            let reset = view_changed || !self.drawn.contains(&entity);
+            self.dispatches.push((
+                entity,
+                DebugPushConstants {
+                    view: view.mode(),
+                    reset: reset as u32,
+                },
+            ));
+            drawn.insert(entity);
+        }
+        self.drawn = drawn;
+    }
+
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let Some(worlds) = &self.worlds else {
+            return Ok(());
+        };
+        if self.dispatches.is_empty() {
+            return Ok(());
+        }
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let pipeline = world.resource::<DebugPipeline>();
+
+        for (entity, _) in &self.dispatches {
+            if let Ok((_, _, debug_bind_group)) = worlds.get_manual(world, *entity) {
+                render_context.command_encoder().clear_buffer(&debug_bind_group.chunk_buffer, 0, None);
+            }
+        }
+
+        let mut pass = render_context
+            .command_encoder()
+            .begin_compute_pass(&ComputePassDescriptor { label: Some("cpd-debug") });
+
+        for (entity, push_constants) in &self.dispatches {
+            let Ok((_, pixel_world, debug_bind_group)) = worlds.get_manual(world, *entity) else { continue };
+            let workgroups = pixel_world.workgroups();
+            pass.set_bind_group(0, &debug_bind_group.bind_group, &[]);
+            for id in [pipeline.track_pipeline, pipeline.draw_pipeline] {
+                pass.set_pipeline(pipeline_cache.get_compute_pipeline(id).unwrap());
+                pass.set_push_constants(0, bytemuck::cast_slice(&[*push_constants]));
+                pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,19 +1,49 @@
 use std::borrow::Cow;
 
-use bevy::{app::Plugin, ecs::{system::{Resource, Commands, Res}, world::{FromWorld, World}, schedule::IntoSystemConfigs}, render::{render_resource::{CachedComputePipelineId, BindGroupLayout, PipelineCache, BindGroupLayoutDescriptor, BindGroupLayoutEntry, ShaderStages, BindingType, StorageTextureAccess, TextureFormat, TextureViewDimension, ComputePipelineDescriptor, PushConstantRange, BindGroup, BindGroupDescriptor, BindGroupEntry, BindingResource, CachedPipelineState, ComputePassDescriptor}, renderer::{RenderDevice, RenderContext}, Render, render_asset::RenderAssets, texture::Image, RenderSet, render_graph}, asset::AssetServer, math::Vec2, prelude::{Vec3, Vec4}};
+use bevy::{app::Plugin, ecs::{component::Component, entity::Entity, query::{Has, QueryState, With}, system::{Resource, Commands, Query, Res}, world::{FromWorld, World}, schedule::IntoSystemConfigs}, render::{render_resource::{CachedComputePipelineId, BindGroupLayout, PipelineCache, BindGroupLayoutDescriptor, BindGroupLayoutEntry, ShaderStages, BindingType, StorageTextureAccess, TextureFormat, TextureViewDimension, ComputePipelineDescriptor, PushConstantRange, BindGroup, BindGroupEntries, BindGroupEntry, BindingResource, BufferBindingType, BufferInitDescriptor, BufferUsages, CachedPipelineState, ComputePassDescriptor}, renderer::{RenderDevice, RenderContext}, Render, render_asset::RenderAssets, texture::Image, RenderSet, render_graph}, asset::AssetServer, core::FrameCount, math::Vec2, prelude::Vec4};
 
-use crate::{input::DrawingParams, GameOfLifeImage, SIM_SIZE, WORKGROUP_SIZE, CurrentElement};
-
-use super::automata::AutomataImageBindGroup;
+use crate::{brush::{BrushMaterial, BrushMode, BrushPattern}, commands::WorldEdits, input::DrawingParams, shader_contract::{push_constant_layout, PushConstantLayout}, tools::{DrawShape, DrawTool, ShapeKind}, CurrentElement, PixelWorld, PrimaryPixelWorld};
 
 // const AIR_COLOR = vec4<f32>(0.02, 0.02, 0.02, 1.0);
 // const SAND_COLOR = vec4<f32>(0.8, 0.8, 0.2, 1.0); 
 // const WATER_COLOR = vec4<f32>(0.2, 0.2, 0.8, 1.0);
 // const ROCK_COLOR = vec4<f32>(0.4, 0.4, 0.4, 1.0);
-const AIR: Vec4 = Vec4::new(0.02, 0.02, 0.02, 1.0);
-const SAND: Vec4 = Vec4::new(0.8, 0.8, 0.2, 1.0);
-const WATER: Vec4 = Vec4::new(0.2, 0.2, 0.8, 1.0);
-const ROCK: Vec4 = Vec4::new(0.4, 0.4, 0.4, 1.0);
+pub const AIR: Vec4 = Vec4::new(0.02, 0.02, 0.02, 1.0);
+pub const SAND: Vec4 = Vec4::new(0.8, 0.8, 0.2, 1.0);
+pub const WATER: Vec4 = Vec4::new(0.2, 0.2, 0.8, 1.0);
+pub const ROCK: Vec4 = Vec4::new(0.4, 0.4, 0.4, 1.0);
+
+// keep in sync with the SHAPE_* constants in draw.wgsl
+const SHAPE_SEGMENT: u32 = 0;
+const SHAPE_RECTANGLE: u32 = 1;
+const SHAPE_ELLIPSE: u32 = 2;
+
+// keep in sync with the MATERIAL_* constants in draw.wgsl
+const MATERIAL_SINGLE: u32 = 0;
+const MATERIAL_MIX: u32 = 1;
+const MATERIAL_CHECKER: u32 = 2;
+const MATERIAL_STRIPES: u32 = 3;
+
+// keep in sync with the MODE_* constants in draw.wgsl
+const MODE_OVERWRITE: u32 = 0;
+const MODE_ONLY_EMPTY: u32 = 1;
+const MODE_REPLACE_ONLY: u32 = 2;
+const MODE_PROTECT: u32 = 3;
+
+/// Constants above as named in draw.wgsl, checked by `tests/shader_contract.rs`.
+pub(crate) const SHADER_CONSTANTS: [(&str, u32); 11] = [
+    ("SHAPE_SEGMENT", SHAPE_SEGMENT),
+    ("SHAPE_RECTANGLE", SHAPE_RECTANGLE),
+    ("SHAPE_ELLIPSE", SHAPE_ELLIPSE),
+    ("MATERIAL_SINGLE", MATERIAL_SINGLE),
+    ("MATERIAL_MIX", MATERIAL_MIX),
+    ("MATERIAL_CHECKER", MATERIAL_CHECKER),
+    ("MATERIAL_STRIPES", MATERIAL_STRIPES),
+    ("MODE_OVERWRITE", MODE_OVERWRITE),
+    ("MODE_ONLY_EMPTY", MODE_ONLY_EMPTY),
+    ("MODE_REPLACE_ONLY", MODE_REPLACE_ONLY),
+    ("MODE_PROTECT", MODE_PROTECT),
+];
 
 pub struct DrawPipelinePlugin;
 
@@ -21,10 +51,11 @@ impl Plugin for DrawPipelinePlugin{
     fn build(&self, app: &mut bevy::prelude::App) {
         app
         .init_resource::<DrawPipeline>()
-        .add_systems(Render, prepare_bind_group.in_set(RenderSet::PrepareBindGroups));
+        .add_systems(Render, (prepare_bind_group, prepare_edits).in_set(RenderSet::PrepareBindGroups));
     }
 }
 
+/// One shape for the draw shader, pushed for the player's drawing and stored in a buffer for the [`WorldEdits`].
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct DrawPushConstants {
@@ -32,15 +63,102 @@ pub struct DrawPushConstants {
     draw_end: [f32; 2],
     draw_radius: f32,
     element: u32,
+    shape: u32,
+    hollow: u32,
+    material: u32,
+    material_elements: u32,
+    material_weights: u32,
+    material_count: u32,
+    material_scale: u32,
+    seed: u32,
+    mode: u32,
+    mode_element: u32,
 }
 
 impl DrawPushConstants {
+    /// Layout the `PushConstants` struct in draw.wgsl must match.
+    pub(crate) fn layout() -> PushConstantLayout {
+        push_constant_layout!("shaders/draw.wgsl", Self {
+            draw_start,
+            draw_end,
+            draw_radius,
+            element,
+            shape,
+            hollow,
+            material,
+            material_elements,
+            material_weights,
+            material_count,
+            material_scale,
+            seed,
+            mode,
+            mode_element,
+        })
+    }
+
     pub fn new(draw_start: Vec2, draw_end: Vec2, draw_radius: f32, element: u32) -> Self {
         Self {
             draw_radius,
             draw_end: draw_end.to_array(),
             draw_start: draw_start.to_array(),
             element,
+            shape: SHAPE_SEGMENT,
+            hollow: 0,
+            material: MATERIAL_SINGLE,
+            material_elements: 0,
+            material_weights: 0,
+            material_count: 0,
+            material_scale: 1,
+            seed: 0,
+            mode: MODE_OVERWRITE,
+            mode_element: 0,
+        }
+    }
+
+    /// Restricts which of the covered cells are painted.
+    pub fn with_mode(self, mode: BrushMode) -> Self {
+        let (mode, mode_element) = match mode {
+            BrushMode::Overwrite => (MODE_OVERWRITE, 0),
+            BrushMode::OnlyEmpty => (MODE_ONLY_EMPTY, 0),
+            BrushMode::ReplaceOnly(element) => (MODE_REPLACE_ONLY, element.index()),
+            BrushMode::Protect(element) => (MODE_PROTECT, element.index()),
+        };
+        Self {
+            mode,
+            mode_element,
+            ..self
+        }
+    }
+
+    /// Paints with `material` instead of the single `element`, `seed` varies the random mixes.
+    pub fn with_material(self, material: &BrushMaterial, seed: u32) -> Self {
+        let kind = match material {
+            BrushMaterial::Single => MATERIAL_SINGLE,
+            BrushMaterial::Mix(_) => MATERIAL_MIX,
+            BrushMaterial::Pattern { pattern: BrushPattern::Checker, .. } => MATERIAL_CHECKER,
+            BrushMaterial::Pattern { pattern: BrushPattern::Stripes, .. } => MATERIAL_STRIPES,
+        };
+        Self {
+            material: kind,
+            material_elements: material.packed_elements(),
+            material_weights: material.packed_weights(),
+            material_count: material.element_count(),
+            material_scale: material.scale(),
+            seed,
+            ..self
+        }
+    }
+
+    pub fn from_shape(shape: &DrawShape) -> Self {
+        let kind = match shape.kind {
+            ShapeKind::Line => SHAPE_SEGMENT,
+            ShapeKind::Rectangle => SHAPE_RECTANGLE,
+            ShapeKind::Ellipse => SHAPE_ELLIPSE,
+        };
+        Self {
+            shape: kind,
+            hollow: shape.hollow as u32,
+            ..Self::new(shape.start, shape.end, shape.thickness, shape.element.index())
         }
     }
 }
@@ -49,7 +167,9 @@ impl DrawPushConstants {
 #[derive(Resource)]
 pub struct DrawPipeline{
     draw_pipeline: CachedComputePipelineId,
+    edits_pipeline: CachedComputePipelineId,
     draw_bind_group_layout: BindGroupLayout,
+    edits_bind_group_layout: BindGroupLayout,
 }
 
 
@@ -74,8 +194,34 @@ impl FromWorld for DrawPipeline {
                     }],
                 });
 
+        let edits_bind_group_layout =
+            world
+                .resource::<RenderDevice>()
+                .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                    label: Some("Game of Life Edits Bind Group Layout"),
+                    entries: &[BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                });
+
         let brush_shader = world.resource::<AssetServer>().load("shaders/draw.wgsl");
 
+        let edits_pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            shader: brush_shader.clone(),
+            shader_defs: vec![],
+            entry_point: Cow::from("draw_edits"),
+            layout: vec![draw_bind_group_layout.clone(), edits_bind_group_layout.clone()],
+            label: Some(std::borrow::Cow::Borrowed("Game of Life Edits Pipeline")),
+            push_constant_ranges: vec![],
+        });
+
         let draw_pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
             shader: brush_shader,
             shader_defs: vec![],
@@ -91,32 +237,78 @@ impl FromWorld for DrawPipeline {
 
         DrawPipeline {
             draw_pipeline,
+            edits_pipeline,
             draw_bind_group_layout,
+            edits_bind_group_layout,
         }
     }
 }
 
 
-#[derive(Resource)]
-struct DrawBindGroup(pub BindGroup);
+/// Binds the texture of one [`PixelWorld`] for the draw pass.
+#[derive(Component)]
+pub struct DrawBindGroup(pub BindGroup);
 
 pub fn prepare_bind_group(
     mut commands: Commands,
     render_device: Res<RenderDevice>,
     pipeline: Res<DrawPipeline>,
     gpu_images: Res<RenderAssets<Image>>,
-    game_of_life_image: Res<GameOfLifeImage>,
+    worlds: Query<(Entity, &PixelWorld)>,
 ) {
-    let view = &gpu_images.get(&game_of_life_image.0).unwrap();
-    let draw_bind_group = render_device.create_bind_group(
-        Some("Game of Life Draw Bind Group"),
-        &pipeline.draw_bind_group_layout,
-        &[BindGroupEntry {
-            binding: 0,
-            resource: BindingResource::TextureView(&view.texture_view),
-        }],
-    );
-    commands.insert_resource(DrawBindGroup(draw_bind_group));
+    for (entity, pixel_world) in &worlds {
+        let Some(view) = gpu_images.get(&pixel_world.image) else { continue };
+        let draw_bind_group = render_device.create_bind_group(
+            Some("Game of Life Draw Bind Group"),
+            &pipeline.draw_bind_group_layout,
+            &[BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(&view.texture_view),
+            }],
+        );
+        commands.entity(entity).insert(DrawBindGroup(draw_bind_group));
+    }
+}
+
+/// The [`WorldEdits`] made for one [`PixelWorld`] this frame, drawn together in a single pass.
+#[derive(Component)]
+pub struct DrawEditsBindGroup(pub BindGroup);
+
+pub fn prepare_edits(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    pipeline: Res<DrawPipeline>,
+    edits: Res<WorldEdits>,
+    frame_count: Res<FrameCount>,
+    worlds: Query<Entity, With<PixelWorld>>,
+) {
+    if edits.is_empty() {
+        return;
+    }
+    for entity in &worlds {
+        let shapes: Vec<DrawPushConstants> = edits
+            .shapes(entity)
+            .map(|shape| {
+                DrawPushConstants::from_shape(shape)
+                    .with_material(&shape.material, frame_count.0)
+                    .with_mode(shape.mode)
+            })
+            .collect();
+        if shapes.is_empty() {
+            continue;
+        }
+        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("Game of Life Edits Buffer"),
+            contents: bytemuck::cast_slice(&shapes),
+            usage: BufferUsages::STORAGE,
+        });
+        let bind_group = render_device.create_bind_group(
+            Some("Game of Life Edits Bind Group"),
+            &pipeline.edits_bind_group_layout,
+            &BindGroupEntries::single(buffer.as_entire_binding()),
+        );
+        commands.entity(entity).insert(DrawEditsBindGroup(bind_group));
+    }
 }
 
 // ================================== Nodes ================================== //
@@ -125,14 +317,25 @@ pub enum AutomataDrawState {
     Update,
 }
 
+/// Draws the player's brush, strokes and shapes into the [`PrimaryPixelWorld`],
+/// then the [`WorldEdits`] into the worlds they target, all edits of a world in one pass.
 pub struct AutomataDrawNode {
     state: AutomataDrawState,
+    worlds: Option<
+        QueryState<(
+            &'static PixelWorld,
+            &'static DrawBindGroup,
+            Option<&'static DrawEditsBindGroup>,
+            Has<PrimaryPixelWorld>,
+        )>,
+    >,
 }
 
 impl Default for AutomataDrawNode {
     fn default() -> Self {
         Self {
             state: AutomataDrawState::Loading,
+            worlds: None,
         }
     }
 }
@@ -145,14 +348,17 @@ impl render_graph::Node for AutomataDrawNode {
         // if the corresponding pipeline has loaded, transition to the next stage
         match self.state {
             AutomataDrawState::Loading => {
-                if let CachedPipelineState::Ok(_) =
-                    pipeline_cache.get_compute_pipeline_state(pipeline.draw_pipeline)
-                {
+                let ready = [pipeline.draw_pipeline, pipeline.edits_pipeline].into_iter().all(|id| {
+                    matches!(pipeline_cache.get_compute_pipeline_state(id), CachedPipelineState::Ok(_))
+                });
+                if ready {
                     self.state = AutomataDrawState::Update;
                 }
             }
             AutomataDrawState::Update => {}
         }
+
+        self.worlds.get_or_insert_with(|| QueryState::new(world)).update_archetypes(world);
     }
 
     fn run(
@@ -161,55 +367,74 @@ impl render_graph::Node for AutomataDrawNode {
         render_context: &mut RenderContext,
         world: &World,
     ) -> Result<(), render_graph::NodeRunError> {
+        let (AutomataDrawState::Update, Some(worlds)) = (&self.state, &self.worlds) else {
+            return Ok(());
+        };
         let params = &world.resource::<DrawingParams>();
+        let draw_brush = (params.is_drawing && params.tool == DrawTool::Brush) || params.is_erasing;
+        let drawing = draw_brush || !params.shapes.is_empty() || !params.strokes.is_empty();
 
-        if params.is_drawing {
-            let texture_bind_group = &world.resource::<AutomataImageBindGroup>().0;
-            let draw_bind_group = &world.resource::<AutomataImageBindGroup>().0;
-            let pipeline_cache = world.resource::<PipelineCache>();
-            let pipeline = world.resource::<DrawPipeline>();
-
-            let mut pass = render_context
-                .command_encoder()
-                .begin_compute_pass(&ComputePassDescriptor{label: Some("cpd-draw")});
-
-            pass.set_bind_group(0, texture_bind_group, &[]);
-
-            let element_index = if params.element == CurrentElement::AIR{
-                0
-            }else if params.element == CurrentElement::SAND{
-                1
-            }else if params.element == CurrentElement::WATER{
-                2
-            }else if params.element == CurrentElement::ROCK{
-                3
-            }else{
-                0
-            };
-
-            // select the pipeline based on the current state
-            match self.state {
-                AutomataDrawState::Loading => {}
-                AutomataDrawState::Update => {
-                    let draw_pipeline = pipeline_cache
-                        .get_compute_pipeline(pipeline.draw_pipeline)
-                        .unwrap();
-
-                    let pc =
-                        DrawPushConstants::new(params.mouse_pos, params.prev_mouse_pos, 10.0, element_index);
-                    
-                    pass.set_pipeline(draw_pipeline);
-                    pass.set_bind_group(0, draw_bind_group, &[]);
-                    pass.set_push_constants(0, bytemuck::cast_slice(&[pc]));
-                    pass.dispatch_workgroups(
-                        SIM_SIZE.0 / WORKGROUP_SIZE,
-                        SIM_SIZE.1 / WORKGROUP_SIZE,
-                        1,
-                    );
+        let edits = world.resource::<WorldEdits>();
+        if !drawing && edits.is_empty() {
+            return Ok(());
+        }
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let pipeline = world.resource::<DrawPipeline>();
+        let draw_pipeline = pipeline_cache.get_compute_pipeline(pipeline.draw_pipeline).unwrap();
+        let edits_pipeline = pipeline_cache.get_compute_pipeline(pipeline.edits_pipeline).unwrap();
+        let seed = world.resource::<FrameCount>().0;
+
+        let mut pass = render_context
+            .command_encoder()
+            .begin_compute_pass(&ComputePassDescriptor{label: Some("cpd-draw")});
+
+        for (pixel_world, draw_bind_group, edits_bind_group, primary) in worlds.iter_manual(world) {
+            // only the primary world is drawn in by the player
+            let drawing = primary && drawing;
+            let brush = (drawing && draw_brush).then(|| {
+                if params.is_erasing {
+                    DrawPushConstants::new(params.mouse_pos, params.prev_mouse_pos, params.brush_radius, CurrentElement::AIR.index())
+                        .with_material(&BrushMaterial::Single, seed)
+                        .with_mode(BrushMode::Overwrite)
+                } else {
+                    DrawPushConstants::new(params.mouse_pos, params.prev_mouse_pos, params.brush_radius, params.element.index())
+                        .with_material(&params.material, seed)
+                        .with_mode(params.mode)
                 }
+            });
+            let shapes = params
+                .shapes
+                .iter()
+                .filter(|_| drawing)
+                .map(|shape| {
+                    DrawPushConstants::from_shape(shape)
+                        .with_material(&shape.material, seed)
+                        .with_mode(shape.mode)
+                });
+
+            let strokes = params.strokes.iter().filter(|_| drawing).map(|stroke| {
+                DrawPushConstants::new(stroke.end, stroke.start, params.brush_radius, params.element.index())
+                    .with_material(&params.material, seed)
+                    .with_mode(params.mode)
+            });
+
+            let workgroups = pixel_world.workgroups();
+            pass.set_pipeline(draw_pipeline);
+            pass.set_bind_group(0, &draw_bind_group.0, &[]);
+            for pc in brush.into_iter().chain(strokes).chain(shapes) {
+                pass.set_push_constants(0, bytemuck::cast_slice(&[pc]));
+                pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+            }
+
+            // edits made through PixelWorldCommands go last, in the order they were made
+            if let Some(edits_bind_group) = edits_bind_group {
+                pass.set_pipeline(edits_pipeline);
+                pass.set_bind_group(1, &edits_bind_group.0, &[]);
+                pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
             }
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,371 @@
+use std::borrow::Cow;
+
+use bevy::{
+    app::{App, Plugin},
+    asset::AssetServer,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::With,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut, Resource},
+        world::{FromWorld, World},
+    },
+    math::{UVec2, Vec2},
+    render::{
+        render_asset::RenderAssets,
+        render_graph,
+        render_resource::{
+            BindGroup, BindGroupEntries, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+            BindingType, Buffer, BufferBindingType, BufferDescriptor, BufferUsages, CachedComputePipelineId,
+            CachedPipelineState, ComputePassDescriptor, ComputePipelineDescriptor, Extent3d, PipelineCache,
+            PushConstantRange, ShaderStages, StorageTextureAccess, Texture, TextureDescriptor, TextureDimension,
+            TextureFormat, TextureSampleType, TextureUsages, TextureViewDescriptor, TextureViewDimension,
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::Image,
+        Render, RenderSet,
+    },
+};
+
+use crate::{
+    input::DrawingParams,
+    shader_contract::{push_constant_layout, PushConstantLayout},
+    PixelWorld, PrimaryPixelWorld,
+};
+
+/// Spread passes dispatched per frame while a fill is running.
+const FILL_STEPS_PER_FRAME: u32 = 128;
+/// A fill stops growing after this many frames even if it has budget left.
+/// It grows over a snapshot taken when it started, so it covers the region as it was when clicked
+/// while the automata keeps running.
+pub const FILL_MAX_FRAMES: u32 = 30;
+// target color (vec4<f32>) + filled counter, padded to the struct alignment
+const FILL_STATE_SIZE: u64 = 32;
+
+pub struct FillPipelinePlugin;
+
+impl Plugin for FillPipelinePlugin {
+    fn build(&self, render_app: &mut App) {
+        render_app
+            .init_resource::<FillPipeline>()
+            .init_resource::<FillScratch>()
+            .add_systems(Render, prepare_bind_group.in_set(RenderSet::PrepareBindGroups));
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FillPushConstants {
+    seed: [u32; 2],
+    element: u32,
+    budget: u32,
+}
+
+impl FillPushConstants {
+    /// Layout the `PushConstants` struct in fill.wgsl must match.
+    pub(crate) fn layout() -> PushConstantLayout {
+        push_constant_layout!("shaders/fill.wgsl", Self { seed, element, budget })
+    }
+
+    pub fn new(seed: Vec2, element: u32, budget: u32) -> Self {
+        Self {
+            seed: seed.max(Vec2::ZERO).as_uvec2().to_array(),
+            element,
+            budget,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct FillPipeline {
+    seed_pipeline: CachedComputePipelineId,
+    spread_pipeline: CachedComputePipelineId,
+    fill_bind_group_layout: BindGroupLayout,
+    state_buffer: Buffer,
+}
+
+impl FromWorld for FillPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let storage_buffer_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let fill_bind_group_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Flood Fill Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::StorageTexture {
+                        access: StorageTextureAccess::ReadWrite,
+                        format: TextureFormat::Rgba8Unorm,
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                storage_buffer_entry(1),
+                storage_buffer_entry(2),
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let state_buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("Flood Fill State Buffer"),
+            size: FILL_STATE_SIZE,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let shader = world.resource::<AssetServer>().load("shaders/fill.wgsl");
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let push_constant_ranges = vec![PushConstantRange {
+            stages: ShaderStages::COMPUTE,
+            range: 0..std::mem::size_of::<FillPushConstants>() as u32,
+        }];
+
+        let seed_pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            shader: shader.clone(),
+            shader_defs: vec![],
+            entry_point: Cow::from("seed"),
+            layout: vec![fill_bind_group_layout.clone()],
+            label: Some(Cow::Borrowed("Flood Fill Seed Pipeline")),
+            push_constant_ranges: push_constant_ranges.clone(),
+        });
+        let spread_pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            shader,
+            shader_defs: vec![],
+            entry_point: Cow::from("spread"),
+            layout: vec![fill_bind_group_layout.clone()],
+            label: Some(Cow::Borrowed("Flood Fill Spread Pipeline")),
+            push_constant_ranges,
+        });
+
+        FillPipeline {
+            seed_pipeline,
+            spread_pipeline,
+            fill_bind_group_layout,
+            state_buffer,
+        }
+    }
+}
+
+/// Cells a fill reached and the world as it was when the fill started, sized for the world it runs on.
+#[derive(Default, Resource)]
+struct FillScratch(Option<FillBuffers>);
+
+struct FillBuffers {
+    size: UVec2,
+    mask: Buffer,
+    snapshot: Texture,
+}
+
+/// Binds a world for the flood fill pass, only the [`PrimaryPixelWorld`] the player fills in gets one.
+#[derive(Component)]
+pub struct FillBindGroup(pub BindGroup);
+
+fn prepare_bind_group(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    pipeline: Res<FillPipeline>,
+    mut scratch: ResMut<FillScratch>,
+    gpu_images: Res<RenderAssets<Image>>,
+    worlds: Query<(Entity, &PixelWorld), With<PrimaryPixelWorld>>,
+) {
+    for (entity, pixel_world) in &worlds {
+        let Some(view) = gpu_images.get(&pixel_world.image) else { continue };
+        let size = pixel_world.size;
+        let reused = scratch.0.take().filter(|buffers| buffers.size == size).unwrap_or_else(|| FillBuffers {
+            size,
+            mask: render_device.create_buffer(&BufferDescriptor {
+                label: Some("Flood Fill Mask Buffer"),
+                size: (size.x * size.y) as u64 * std::mem::size_of::<u32>() as u64,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            snapshot: render_device.create_texture(&TextureDescriptor {
+                label: Some("Flood Fill Snapshot Texture"),
+                size: Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba8Unorm,
+                usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            }),
+        });
+        let buffers = scratch.0.insert(reused);
+        let snapshot_view = buffers.snapshot.create_view(&TextureViewDescriptor::default());
+        let bind_group = render_device.create_bind_group(
+            Some("Flood Fill Bind Group"),
+            &pipeline.fill_bind_group_layout,
+            &BindGroupEntries::sequential((
+                &view.texture_view,
+                buffers.mask.as_entire_binding(),
+                pipeline.state_buffer.as_entire_binding(),
+                &snapshot_view,
+            )),
+        );
+        commands.entity(entity).insert(FillBindGroup(bind_group));
+    }
+}
+
+// ================================== Nodes ================================== //
+pub enum FloodFillState {
+    Loading,
+    Update,
+}
+
+/// A fill in progress, grown a few steps every frame until it runs out of frames or budget.
+#[derive(Clone, Copy)]
+struct FillJob {
+    /// Entity of the [`PixelWorld`] being filled
+    world: Entity,
+    push_constants: FillPushConstants,
+    frame: u32,
+}
+
+pub struct FloodFillNode {
+    state: FloodFillState,
+    job: Option<FillJob>,
+}
+
+impl Default for FloodFillNode {
+    fn default() -> Self {
+        Self {
+            state: FloodFillState::Loading,
+            job: None,
+        }
+    }
+}
+
+impl render_graph::Node for FloodFillNode {
+    fn update(&mut self, world: &mut World) {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let pipeline = world.resource::<FillPipeline>();
+
+        match self.state {
+            FloodFillState::Loading => {
+                let seed_ready = matches!(
+                    pipeline_cache.get_compute_pipeline_state(pipeline.seed_pipeline),
+                    CachedPipelineState::Ok(_)
+                );
+                let spread_ready = matches!(
+                    pipeline_cache.get_compute_pipeline_state(pipeline.spread_pipeline),
+                    CachedPipelineState::Ok(_)
+                );
+                if seed_ready && spread_ready {
+                    self.state = FloodFillState::Update;
+                }
+            }
+            FloodFillState::Update => {
+                if let Some(job) = &mut self.job {
+                    job.frame += 1;
+                    if job.frame >= FILL_MAX_FRAMES {
+                        self.job = None;
+                    }
+                }
+
+                let params = world.resource::<DrawingParams>();
+                if let Some(seed) = params.fill_seed {
+                    let push_constants = FillPushConstants::new(seed, params.element.index(), params.fill_budget);
+                    // the player only fills in the primary world
+                    let primary = world.query_filtered::<Entity, With<PrimaryPixelWorld>>().iter(world).next();
+                    if let Some(primary) = primary {
+                        self.job = Some(FillJob {
+                            world: primary,
+                            push_constants,
+                            frame: 0,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let Some(job) = &self.job else {
+            return Ok(());
+        };
+
+        let (Some(pixel_world), Some(fill_bind_group), Some(buffers)) = (
+            world.get::<PixelWorld>(job.world),
+            world.get::<FillBindGroup>(job.world),
+            &world.resource::<FillScratch>().0,
+        ) else {
+            return Ok(());
+        };
+        let Some(gpu_image) = world.resource::<RenderAssets<Image>>().get(&pixel_world.image) else {
+            return Ok(());
+        };
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let pipeline = world.resource::<FillPipeline>();
+        let workgroups = pixel_world.workgroups();
+
+        if job.frame == 0 {
+            let encoder = render_context.command_encoder();
+            encoder.clear_buffer(&buffers.mask, 0, None);
+            encoder.clear_buffer(&pipeline.state_buffer, 0, None);
+            // the fill grows over the world as it is now, while the automata keeps moving the live cells
+            encoder.copy_texture_to_texture(
+                gpu_image.texture.as_image_copy(),
+                buffers.snapshot.as_image_copy(),
+                Extent3d {
+                    width: pixel_world.size.x,
+                    height: pixel_world.size.y,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        let mut pass = render_context
+            .command_encoder()
+            .begin_compute_pass(&ComputePassDescriptor { label: Some("cpd-fill") });
+
+        pass.set_bind_group(0, &fill_bind_group.0, &[]);
+
+        if job.frame == 0 {
+            let seed_pipeline = pipeline_cache.get_compute_pipeline(pipeline.seed_pipeline).unwrap();
+            pass.set_pipeline(seed_pipeline);
+            pass.set_push_constants(0, bytemuck::cast_slice(&[job.push_constants]));
+            pass.dispatch_workgroups(1, 1, 1);
+        }
+
+        let spread_pipeline = pipeline_cache.get_compute_pipeline(pipeline.spread_pipeline).unwrap();
+        pass.set_pipeline(spread_pipeline);
+        pass.set_push_constants(0, bytemuck::cast_slice(&[job.push_constants]));
+        for _ in 0..FILL_STEPS_PER_FRAME {
+            pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,232 @@
+use std::borrow::Cow;
+
+use bevy::{
+    app::{App, Plugin},
+    asset::AssetServer,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::QueryState,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut, Resource},
+        world::{FromWorld, World},
+    },
+    render::{
+        render_asset::RenderAssets,
+        render_graph,
+        render_resource::{
+            BindGroup, BindGroupEntries, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+            BindingType, Buffer, BufferBindingType, BufferDescriptor, BufferUsages, CachedComputePipelineId,
+            CachedPipelineState, ComputePassDescriptor, ComputePipelineDescriptor, PipelineCache, ShaderStages,
+            StorageTextureAccess, TextureFormat, TextureViewDimension,
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::Image,
+        Render, RenderSet,
+    },
+    utils::HashMap,
+};
+
+use crate::{pipeline::transfer::BufferReadbacks, PixelWorld};
+
+/// Label the element counts are read back under.
+pub const ELEMENT_COUNTS_LABEL: &str = "element_counts";
+/// One counter per element plus one for cells matching no element.
+pub const ELEMENT_COUNTERS: usize = 5;
+const COUNTS_SIZE: u64 = (ELEMENT_COUNTERS * std::mem::size_of::<u32>()) as u64;
+
+pub struct StatsPipelinePlugin;
+
+impl Plugin for StatsPipelinePlugin {
+    fn build(&self, render_app: &mut App) {
+        render_app.init_resource::<StatsPipeline>().init_resource::<CountsBuffers>().add_systems(
+            Render,
+            (
+                prepare_counts_buffers.in_set(RenderSet::PrepareResources),
+                request_counts.in_set(RenderSet::Queue),
+                prepare_bind_group.in_set(RenderSet::PrepareBindGroups),
+            ),
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct StatsPipeline {
+    count_pipeline: CachedComputePipelineId,
+    stats_bind_group_layout: BindGroupLayout,
+}
+
+impl FromWorld for StatsPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let stats_bind_group_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Element Stats Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::StorageTexture {
+                        access: StorageTextureAccess::ReadWrite,
+                        format: TextureFormat::Rgba8Unorm,
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = world.resource::<AssetServer>().load("shaders/stats.wgsl");
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let count_pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            shader,
+            shader_defs: vec![],
+            entry_point: Cow::from("count"),
+            layout: vec![stats_bind_group_layout.clone()],
+            label: Some(Cow::Borrowed("Element Stats Pipeline")),
+            push_constant_ranges: vec![],
+        });
+
+        StatsPipeline {
+            count_pipeline,
+            stats_bind_group_layout,
+        }
+    }
+}
+
+/// Element counts of every world, kept across frames unlike the render world entities.
+#[derive(Default, Resource)]
+struct CountsBuffers(HashMap<Entity, CountsBuffer>);
+
+struct CountsBuffer {
+    buffer: Buffer,
+    /// The world ticked, and so was counted, this frame
+    counted: bool,
+}
+
+/// Binds one [`PixelWorld`] and its counts for the stats pass.
+#[derive(Component)]
+pub struct StatsBindGroup {
+    bind_group: BindGroup,
+    counts_buffer: Buffer,
+}
+
+fn prepare_counts_buffers(
+    render_device: Res<RenderDevice>,
+    mut buffers: ResMut<CountsBuffers>,
+    worlds: Query<(Entity, &PixelWorld)>,
+) {
+    buffers.0.retain(|entity, _| worlds.contains(*entity));
+    for (entity, pixel_world) in &worlds {
+        let counts = buffers.0.entry(entity).or_insert_with(|| CountsBuffer {
+            buffer: render_device.create_buffer(&BufferDescriptor {
+                label: Some("Element Counts Buffer"),
+                size: COUNTS_SIZE,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            counted: false,
+        });
+        counts.counted = pixel_world.clock.ticks_due > 0;
+    }
+}
+
+// the counts of the previous frame are still in the buffers when the readback copies them,
+// worlds that did not tick were not counted again
+fn request_counts(buffers: Res<CountsBuffers>, mut readbacks: ResMut<BufferReadbacks>) {
+    for (world, counts) in &buffers.0 {
+        if counts.counted {
+            readbacks.request(ELEMENT_COUNTS_LABEL, *world, &counts.buffer, COUNTS_SIZE);
+        }
+    }
+}
+
+fn prepare_bind_group(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    pipeline: Res<StatsPipeline>,
+    buffers: Res<CountsBuffers>,
+    gpu_images: Res<RenderAssets<Image>>,
+    worlds: Query<(Entity, &PixelWorld)>,
+) {
+    for (entity, pixel_world) in &worlds {
+        let (Some(view), Some(counts)) = (gpu_images.get(&pixel_world.image), buffers.0.get(&entity)) else {
+            continue;
+        };
+        let bind_group = render_device.create_bind_group(
+            Some("Element Stats Bind Group"),
+            &pipeline.stats_bind_group_layout,
+            &BindGroupEntries::sequential((&view.texture_view, counts.buffer.as_entire_binding())),
+        );
+        commands.entity(entity).insert(StatsBindGroup {
+            bind_group,
+            counts_buffer: counts.buffer.clone(),
+        });
+    }
+}
+
+// ================================== Nodes ================================== //
+/// Counts the cells of every element in every world the automata stepped this frame.
+#[derive(Default)]
+pub struct StatsNode {
+    ready: bool,
+    worlds: Option<QueryState<(&'static PixelWorld, &'static StatsBindGroup)>>,
+}
+
+impl render_graph::Node for StatsNode {
+    fn update(&mut self, world: &mut World) {
+        if !self.ready {
+            let pipeline_cache = world.resource::<PipelineCache>();
+            let pipeline = world.resource::<StatsPipeline>();
+            self.ready = matches!(
+                pipeline_cache.get_compute_pipeline_state(pipeline.count_pipeline),
+                CachedPipelineState::Ok(_)
+            );
+        }
+
+        self.worlds.get_or_insert_with(|| QueryState::new(world)).update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let (true, Some(worlds)) = (self.ready, &self.worlds) else {
+            return Ok(());
+        };
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let pipeline = world.resource::<StatsPipeline>();
+
+        // a world that did not tick keeps its counts
+        let ticked = || worlds.iter_manual(world).filter(|(pixel_world, _)| pixel_world.clock.ticks_due > 0);
+        for (_, stats_bind_group) in ticked() {
+            render_context.command_encoder().clear_buffer(&stats_bind_group.counts_buffer, 0, None);
+        }
+
+        let mut pass = render_context
+            .command_encoder()
+            .begin_compute_pass(&ComputePassDescriptor { label: Some("cpd-stats") });
+
+        pass.set_pipeline(pipeline_cache.get_compute_pipeline(pipeline.count_pipeline).unwrap());
+        for (pixel_world, stats_bind_group) in ticked() {
+            let workgroups = pixel_world.workgroups();
+            pass.set_bind_group(0, &stats_bind_group.bind_group, &[]);
+            pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,462 @@
+use std::sync::{
+    mpsc::{channel, Receiver, Sender},
+    Arc, Mutex, OnceLock,
+};
+
+use bevy::{
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_asset::RenderAssets,
+        render_graph,
+        render_resource::{
+            Buffer, BufferDescriptor, BufferUsages, Extent3d, ImageCopyBuffer, ImageCopyTexture,
+            ImageDataLayout, MapMode, Origin3d, TextureAspect,
+        },
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        Render, RenderApp, RenderSet,
+    },
+    utils::HashMap,
+};
+
+use crate::{grid::CellGrid, PixelWorld};
+
+const BYTES_PER_CELL: u32 = 4;
+/// Idle staging buffers kept around per size, any further ones are freed.
+const MAX_POOLED_BUFFERS: usize = 4;
+
+/// Moves cells between the CPU and the world texture:
+/// region reads come back a frame or more later as [`RegionReadback`] events, or [`ReadbackFailed`] ones,
+/// region writes land before the next draw pass.
+/// Render world passes can also copy their own buffers back through [`BufferReadbacks`].
+pub struct TransferPlugin;
+
+impl Plugin for TransferPlugin {
+    fn build(&self, app: &mut App) {
+        let (sender, receiver) = channel();
+
+        app.init_resource::<ReadbackRequests>()
+            .init_resource::<RegionWrites>()
+            .insert_resource(ReadbackReceiver(Mutex::new(receiver)))
+            .add_event::<RegionReadback>()
+            .add_event::<ReadbackFailed>()
+            .add_event::<BufferReadback>()
+            .add_plugins(ExtractResourcePlugin::<ReadbackRequests>::default())
+            .add_plugins(ExtractResourcePlugin::<RegionWrites>::default())
+            .add_systems(First, (clear_transfers, receive_readbacks));
+
+        app.sub_app_mut(RenderApp)
+            .insert_resource(ReadbackSender(sender))
+            .init_resource::<ReadbackBuffers>()
+            .init_resource::<BufferReadbacks>()
+            .add_systems(
+                Render,
+                (
+                    apply_region_writes.in_set(RenderSet::Queue),
+                    prepare_readback_buffers.in_set(RenderSet::Prepare),
+                    map_readback_buffers.in_set(RenderSet::Cleanup),
+                ),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ReadbackId(u64);
+
+#[derive(Clone, Debug)]
+pub struct ReadbackRequest {
+    pub id: ReadbackId,
+    /// Entity of the [`PixelWorld`] to read
+    pub world: Entity,
+    pub region: URect,
+}
+
+/// Regions of world textures to copy back to the CPU this frame.
+#[derive(Default, Resource, ExtractResource, Clone)]
+pub struct ReadbackRequests {
+    next_id: u64,
+    pub requests: Vec<ReadbackRequest>,
+}
+
+impl ReadbackRequests {
+    /// Requests a copy of `region` of the [`PixelWorld`] on `world`, clamped to that world,
+    /// answered by a [`RegionReadback`] with the returned id,
+    /// or a [`ReadbackFailed`] one when `world` holds no [`PixelWorld`] or the copy could not be read.
+    pub fn request(&mut self, world: Entity, region: URect) -> ReadbackId {
+        let id = ReadbackId(self.next_id);
+        self.next_id += 1;
+        self.requests.push(ReadbackRequest { id, world, region });
+        id
+    }
+}
+
+/// `region` clamped to a world of `size` cells, keeping at least one cell.
+fn clamp_region(region: URect, size: UVec2) -> URect {
+    let mut region = region.intersect(URect::from_corners(UVec2::ZERO, size));
+    region.min = region.min.min(size - UVec2::ONE);
+    region.max = region.max.max(region.min + UVec2::ONE);
+    region
+}
+
+/// Cells copied back from a world texture, as requested through [`ReadbackRequests`].
+#[derive(Event, Clone, Debug)]
+pub struct RegionReadback {
+    pub id: ReadbackId,
+    pub world: Entity,
+    /// Requested region, clamped to the world
+    pub region: URect,
+    /// Tightly packed RGBA8 pixels, row by row
+    pub data: Vec<u8>,
+}
+
+impl RegionReadback {
+    pub fn to_grid(&self) -> CellGrid {
+        CellGrid::from_rgba(self.region.size(), &self.data)
+    }
+}
+
+/// A region readback that will never arrive, so whoever waits on its id can stop doing so.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ReadbackFailed {
+    pub id: ReadbackId,
+}
+
+/// Contents of a render world buffer, as requested through [`BufferReadbacks`].
+#[derive(Event, Clone, Debug)]
+pub struct BufferReadback {
+    /// Label the buffer was requested under
+    pub label: &'static str,
+    /// Entity of the [`PixelWorld`] the buffer belongs to
+    pub world: Entity,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RegionWrite {
+    /// Entity of the [`PixelWorld`] to write into
+    pub world: Entity,
+    pub origin: UVec2,
+    pub grid: CellGrid,
+}
+
+/// Blocks of cells to write into world textures this frame, they must lie inside their world.
+#[derive(Default, Resource, ExtractResource, Clone)]
+pub struct RegionWrites(pub Vec<RegionWrite>);
+
+enum Readback {
+    Region(RegionReadback),
+    Buffer(BufferReadback),
+    Failed(ReadbackFailed),
+}
+
+#[derive(Resource)]
+struct ReadbackReceiver(Mutex<Receiver<Readback>>);
+
+#[derive(Resource)]
+struct ReadbackSender(Sender<Readback>);
+
+// requests and writes only live for the frame they were made in
+fn clear_transfers(mut requests: ResMut<ReadbackRequests>, mut writes: ResMut<RegionWrites>) {
+    if !requests.requests.is_empty() {
+        requests.requests.clear();
+    }
+    if !writes.0.is_empty() {
+        writes.0.clear();
+    }
+}
+
+fn receive_readbacks(
+    receiver: Res<ReadbackReceiver>,
+    mut region_readbacks: EventWriter<RegionReadback>,
+    mut buffer_readbacks: EventWriter<BufferReadback>,
+    mut failed_readbacks: EventWriter<ReadbackFailed>,
+) {
+    let receiver = receiver.0.lock().unwrap();
+    for readback in receiver.try_iter() {
+        match readback {
+            Readback::Region(readback) => region_readbacks.send(readback),
+            Readback::Buffer(readback) => buffer_readbacks.send(readback),
+            Readback::Failed(failed) => failed_readbacks.send(failed),
+        }
+    }
+}
+
+// ================================== Render world ================================== //
+/// Buffers to copy back to the main world this frame, answered by [`BufferReadback`] events.
+/// Request during [`RenderSet::Queue`], the copy happens before anything is drawn this frame.
+/// Buffers are requested again every tick, so a failed copy is only logged.
+#[derive(Default, Resource)]
+pub struct BufferReadbacks {
+    requests: Vec<(&'static str, Entity, Buffer, u64)>,
+}
+
+impl BufferReadbacks {
+    /// Copies the first `size` bytes of `buffer` kept for the world on `world`,
+    /// the buffer needs [`BufferUsages::COPY_SRC`].
+    pub fn request(&mut self, label: &'static str, world: Entity, buffer: &Buffer, size: u64) {
+        self.requests.push((label, world, buffer.clone(), size));
+    }
+}
+
+enum ReadbackSource {
+    Region {
+        /// Request with its region clamped to the world
+        request: ReadbackRequest,
+        image: Handle<Image>,
+        padded_bytes_per_row: u32,
+    },
+    Buffer {
+        label: &'static str,
+        world: Entity,
+        buffer: Buffer,
+        size: u64,
+    },
+}
+
+struct StagedReadback {
+    source: ReadbackSource,
+    buffer: Buffer,
+    size: u64,
+    /// Set once the GPU mapped the buffer, or failed to
+    mapped: Arc<OnceLock<Result<(), wgpu::BufferAsyncError>>>,
+}
+
+#[derive(Default, Resource)]
+struct ReadbackBuffers {
+    /// Copied into by [`ReadbackNode`] this frame
+    pending: Vec<StagedReadback>,
+    /// Waiting for the GPU to map them
+    in_flight: Vec<StagedReadback>,
+    /// Unmapped staging buffers free for reuse, by size
+    pool: HashMap<u64, Vec<Buffer>>,
+}
+
+impl ReadbackBuffers {
+    fn stage(&mut self, render_device: &RenderDevice, source: ReadbackSource, size: u64) {
+        let buffer = self.pool.get_mut(&size).and_then(Vec::pop).unwrap_or_else(|| {
+            render_device.create_buffer(&BufferDescriptor {
+                label: Some("Readback Staging Buffer"),
+                size,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+        self.pending.push(StagedReadback {
+            source,
+            buffer,
+            size,
+            mapped: Arc::new(OnceLock::new()),
+        });
+    }
+}
+
+fn apply_region_writes(
+    writes: Res<RegionWrites>,
+    render_queue: Res<RenderQueue>,
+    gpu_images: Res<RenderAssets<Image>>,
+    worlds: Query<&PixelWorld>,
+) {
+    for write in &writes.0 {
+        if write.grid.is_empty() {
+            continue;
+        }
+        let Some(gpu_image) = worlds.get(write.world).ok().and_then(|world| gpu_images.get(&world.image)) else {
+            continue;
+        };
+        render_queue.write_texture(
+            ImageCopyTexture {
+                texture: &gpu_image.texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: write.origin.x,
+                    y: write.origin.y,
+                    z: 0,
+                },
+                aspect: TextureAspect::All,
+            },
+            &write.grid.to_rgba(),
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(write.grid.size.x * BYTES_PER_CELL),
+                rows_per_image: None,
+            },
+            Extent3d {
+                width: write.grid.size.x,
+                height: write.grid.size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+fn prepare_readback_buffers(
+    requests: Res<ReadbackRequests>,
+    mut buffer_requests: ResMut<BufferReadbacks>,
+    render_device: Res<RenderDevice>,
+    mut buffers: ResMut<ReadbackBuffers>,
+    sender: Res<ReadbackSender>,
+    worlds: Query<&PixelWorld>,
+) {
+    for request in &requests.requests {
+        let Ok(world) = worlds.get(request.world) else {
+            warn!("Readback requested from {:?}, which holds no pixel world", request.world);
+            let _ = sender.0.send(Readback::Failed(ReadbackFailed { id: request.id }));
+            continue;
+        };
+        let region = clamp_region(request.region, world.size);
+        let size = region.size();
+        let padded_bytes_per_row =
+            RenderDevice::align_copy_bytes_per_row((size.x * BYTES_PER_CELL) as usize) as u32;
+        buffers.stage(
+            &render_device,
+            ReadbackSource::Region {
+                request: ReadbackRequest {
+                    region,
+                    ..request.clone()
+                },
+                image: world.image.clone(),
+                padded_bytes_per_row,
+            },
+            (padded_bytes_per_row * size.y) as u64,
+        );
+    }
+
+    for (label, world, buffer, size) in buffer_requests.requests.drain(..) {
+        buffers.stage(&render_device, ReadbackSource::Buffer { label, world, buffer, size }, size);
+    }
+}
+
+fn map_readback_buffers(
+    render_device: Res<RenderDevice>,
+    sender: Res<ReadbackSender>,
+    mut buffers: ResMut<ReadbackBuffers>,
+) {
+    let ReadbackBuffers { pending, in_flight, pool } = &mut *buffers;
+    for staged in pending.drain(..) {
+        let mapped = staged.mapped.clone();
+        render_device.map_buffer(&staged.buffer.slice(..), MapMode::Read, move |result| {
+            let _ = mapped.set(result);
+        });
+        in_flight.push(staged);
+    }
+
+    render_device.poll(wgpu::Maintain::Poll);
+
+    in_flight.retain(|staged| {
+        match staged.mapped.get() {
+            None => return true,
+            Some(Err(error)) => {
+                // nothing to read, and a buffer that failed to map is not worth reusing
+                warn!("Failed to map readback buffer, dropping the readback {error:?}");
+                if let ReadbackSource::Region { request, .. } = &staged.source {
+                    let _ = sender.0.send(Readback::Failed(ReadbackFailed { id: request.id }));
+                }
+                return false;
+            }
+            Some(Ok(())) => {}
+        }
+
+        let readback = {
+            let mapped_range = staged.buffer.slice(..).get_mapped_range();
+            match &staged.source {
+                ReadbackSource::Region {
+                    request,
+                    padded_bytes_per_row,
+                    ..
+                } => {
+                    let size = request.region.size();
+                    let row_bytes = (size.x * BYTES_PER_CELL) as usize;
+                    let mut data = Vec::with_capacity(row_bytes * size.y as usize);
+                    for row in mapped_range.chunks(*padded_bytes_per_row as usize) {
+                        data.extend_from_slice(&row[..row_bytes]);
+                    }
+                    Readback::Region(RegionReadback {
+                        id: request.id,
+                        world: request.world,
+                        region: request.region,
+                        data,
+                    })
+                }
+                ReadbackSource::Buffer { label, world, .. } => Readback::Buffer(BufferReadback {
+                    label,
+                    world: *world,
+                    data: mapped_range.to_vec(),
+                }),
+            }
+        };
+        staged.buffer.unmap();
+        let free = pool.entry(staged.size).or_default();
+        if free.len() < MAX_POOLED_BUFFERS {
+            free.push(staged.buffer.clone());
+        }
+
+        // the main world may already be gone while shutting down
+        let _ = sender.0.send(readback);
+        false
+    });
+}
+
+// ================================== Nodes ================================== //
+/// Copies the requested regions and buffers into their staging buffers, before anything is drawn this frame.
+#[derive(Default)]
+pub struct ReadbackNode;
+
+impl render_graph::Node for ReadbackNode {
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let buffers = world.resource::<ReadbackBuffers>();
+        if buffers.pending.is_empty() {
+            return Ok(());
+        }
+
+        let gpu_images = world.resource::<RenderAssets<Image>>();
+        for staged in &buffers.pending {
+            let (region, image, padded_bytes_per_row) = match &staged.source {
+                ReadbackSource::Region {
+                    request,
+                    image,
+                    padded_bytes_per_row,
+                } => (request.region, image, *padded_bytes_per_row),
+                ReadbackSource::Buffer { buffer, size, .. } => {
+                    render_context
+                        .command_encoder()
+                        .copy_buffer_to_buffer(buffer, 0, &staged.buffer, 0, *size);
+                    continue;
+                }
+            };
+            // not uploaded yet
+            let Some(gpu_image) = gpu_images.get(image) else { continue };
+            render_context.command_encoder().copy_texture_to_buffer(
+                ImageCopyTexture {
+                    texture: &gpu_image.texture,
+                    mip_level: 0,
+                    origin: Origin3d {
+                        x: region.min.x,
+                        y: region.min.y,
+                        z: 0,
+                    },
+                    aspect: TextureAspect::All,
+                },
+                ImageCopyBuffer {
+                    buffer: &staged.buffer,
+                    layout: ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(padded_bytes_per_row),
+                        rows_per_image: None,
+                    },
+                },
+                Extent3d {
+                    width: region.width(),
+                    height: region.height(),
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,371 @@
+use std::borrow::Cow;
+
+use bevy::{
+    app::{App, Plugin},
+    asset::AssetServer,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::QueryState,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut, Resource},
+        world::{FromWorld, World},
+    },
+    math::{URect, UVec2},
+    render::{
+        render_asset::RenderAssets,
+        render_graph,
+        render_resource::{
+            BindGroup, BindGroupEntries, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+            BindingType, Buffer, BufferBindingType, BufferDescriptor, BufferInitDescriptor, BufferUsages,
+            CachedComputePipelineId, CachedPipelineState, ComputePassDescriptor, ComputePipelineDescriptor,
+            PipelineCache, PushConstantRange, ShaderStages, StorageTextureAccess, TextureFormat,
+            TextureViewDimension,
+        },
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        texture::Image,
+        Render, RenderSet,
+    },
+    utils::HashMap,
+};
+
+use crate::{
+    pipeline::transfer::BufferReadbacks,
+    shader_contract::{push_constant_layout, PushConstantLayout},
+    triggers::{RegionTriggers, TriggerArea},
+    PixelWorld, WORKGROUP_SIZE,
+};
+
+/// Label the region trigger counts are read back under.
+pub const TRIGGER_COUNTS_LABEL: &str = "trigger_counts";
+/// Triggers counted per world, any further ones are ignored.
+pub const MAX_TRIGGERS: usize = 64;
+/// Counters per trigger, one per element.
+pub(crate) const TRIGGER_COUNTERS: usize = 4;
+/// The generation header followed by the counters of every trigger.
+const COUNTS_SIZE: u64 = ((1 + MAX_TRIGGERS * TRIGGER_COUNTERS) * std::mem::size_of::<u32>()) as u64;
+
+pub struct TriggersPipelinePlugin;
+
+impl Plugin for TriggersPipelinePlugin {
+    fn build(&self, render_app: &mut App) {
+        render_app.init_resource::<TriggersPipeline>().init_resource::<WorldTriggerRegions>().add_systems(
+            Render,
+            (
+                prepare_regions.in_set(RenderSet::PrepareResources),
+                request_counts.in_set(RenderSet::Queue),
+                prepare_bind_group.in_set(RenderSet::PrepareBindGroups),
+            ),
+        );
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TriggerPushConstants {
+    region: u32,
+    generation: u32,
+}
+
+impl TriggerPushConstants {
+    /// Layout the `PushConstants` struct in triggers.wgsl must match.
+    pub(crate) fn layout() -> PushConstantLayout {
+        push_constant_layout!("shaders/triggers.wgsl", Self { region, generation })
+    }
+}
+
+/// A trigger's area as the shader reads it.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuTriggerRegion {
+    min: [u32; 2],
+    max: [u32; 2],
+    mask_offset: u32,
+    has_mask: u32,
+}
+
+#[derive(Resource)]
+pub struct TriggersPipeline {
+    header_pipeline: CachedComputePipelineId,
+    count_pipeline: CachedComputePipelineId,
+    triggers_bind_group_layout: BindGroupLayout,
+}
+
+impl FromWorld for TriggersPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let storage_buffer_entry = |binding, read_only| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let triggers_bind_group_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Region Triggers Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::StorageTexture {
+                        access: StorageTextureAccess::ReadWrite,
+                        format: TextureFormat::Rgba8Unorm,
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                storage_buffer_entry(1, true),
+                storage_buffer_entry(2, true),
+                storage_buffer_entry(3, false),
+            ],
+        });
+
+        let shader = world.resource::<AssetServer>().load("shaders/triggers.wgsl");
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let push_constant_ranges = vec![PushConstantRange {
+            stages: ShaderStages::COMPUTE,
+            range: 0..std::mem::size_of::<TriggerPushConstants>() as u32,
+        }];
+
+        let header_pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            shader: shader.clone(),
+            shader_defs: vec![],
+            entry_point: Cow::from("header"),
+            layout: vec![triggers_bind_group_layout.clone()],
+            label: Some(Cow::Borrowed("Region Triggers Header Pipeline")),
+            push_constant_ranges: push_constant_ranges.clone(),
+        });
+        let count_pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            shader,
+            shader_defs: vec![],
+            entry_point: Cow::from("count"),
+            layout: vec![triggers_bind_group_layout.clone()],
+            label: Some(Cow::Borrowed("Region Triggers Count Pipeline")),
+            push_constant_ranges,
+        });
+
+        TriggersPipeline {
+            header_pipeline,
+            count_pipeline,
+            triggers_bind_group_layout,
+        }
+    }
+}
+
+/// Regions uploaded for the current [`RegionTriggers`] of one world.
+struct TriggerRegions {
+    generation: u32,
+    /// Clipped bounds of every counted trigger, in the order of the regions buffer
+    bounds: Vec<URect>,
+    regions_buffer: Buffer,
+    /// Mask cells of every masked trigger, never empty
+    masks_buffer: Buffer,
+    counts_buffer: Buffer,
+    /// The world ticked, and so was counted, this frame
+    counted: bool,
+}
+
+/// Regions of every world with [`RegionTriggers`], kept across frames unlike the render world entities.
+#[derive(Default, Resource)]
+struct WorldTriggerRegions(HashMap<Entity, TriggerRegions>);
+
+/// Binds one [`PixelWorld`] and its trigger regions for the region triggers pass.
+#[derive(Component)]
+pub struct TriggersBindGroup(pub BindGroup);
+
+fn prepare_regions(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut uploaded: ResMut<WorldTriggerRegions>,
+    worlds: Query<(Entity, &PixelWorld, &RegionTriggers)>,
+) {
+    uploaded.0.retain(|entity, _| worlds.contains(*entity));
+
+    for (entity, pixel_world, triggers) in &worlds {
+        let counted = pixel_world.clock.ticks_due > 0;
+        if let Some(regions) = uploaded.0.get_mut(&entity).filter(|regions| regions.generation == triggers.generation) {
+            regions.counted = counted;
+            continue;
+        }
+
+        let mut regions = Vec::new();
+        let mut bounds = Vec::new();
+        // a zero sized binding is invalid, keep one unused cell
+        let mut masks = vec![0u32];
+        for trigger in triggers.iter().take(MAX_TRIGGERS) {
+            let rect = trigger.area.bounds(pixel_world.size);
+            let masked = !matches!(trigger.area, TriggerArea::Rect(_));
+            let mask_offset = masks.len() as u32;
+            if masked {
+                for y in rect.min.y..rect.max.y {
+                    for x in rect.min.x..rect.max.x {
+                        masks.push(trigger.area.contains(UVec2::new(x, y)) as u32);
+                    }
+                }
+            }
+            regions.push(GpuTriggerRegion {
+                min: rect.min.to_array(),
+                max: rect.max.to_array(),
+                mask_offset,
+                has_mask: masked as u32,
+            });
+            bounds.push(rect);
+        }
+
+        let (regions_buffer, counts_buffer) = match uploaded.0.remove(&entity) {
+            Some(previous) => (previous.regions_buffer, previous.counts_buffer),
+            None => (
+                render_device.create_buffer(&BufferDescriptor {
+                    label: Some("Region Triggers Regions Buffer"),
+                    size: (MAX_TRIGGERS * std::mem::size_of::<GpuTriggerRegion>()) as u64,
+                    usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+                render_device.create_buffer(&BufferDescriptor {
+                    label: Some("Region Triggers Counts Buffer"),
+                    size: COUNTS_SIZE,
+                    usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                }),
+            ),
+        };
+        if !regions.is_empty() {
+            render_queue.write_buffer(&regions_buffer, 0, bytemuck::cast_slice(&regions));
+        }
+        let masks_buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("Region Triggers Masks Buffer"),
+            contents: bytemuck::cast_slice(&masks),
+            usage: BufferUsages::STORAGE,
+        });
+        uploaded.0.insert(
+            entity,
+            TriggerRegions {
+                generation: triggers.generation,
+                bounds,
+                regions_buffer,
+                masks_buffer,
+                counts_buffer,
+                counted,
+            },
+        );
+    }
+}
+
+// the counts of the previous frame are still in the buffers when the readback copies them,
+// worlds that did not tick were not counted again
+fn request_counts(uploaded: Res<WorldTriggerRegions>, mut readbacks: ResMut<BufferReadbacks>) {
+    for (world, regions) in &uploaded.0 {
+        if regions.counted && !regions.bounds.is_empty() {
+            readbacks.request(TRIGGER_COUNTS_LABEL, *world, &regions.counts_buffer, COUNTS_SIZE);
+        }
+    }
+}
+
+fn prepare_bind_group(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    pipeline: Res<TriggersPipeline>,
+    uploaded: Res<WorldTriggerRegions>,
+    gpu_images: Res<RenderAssets<Image>>,
+    worlds: Query<(Entity, &PixelWorld)>,
+) {
+    for (entity, pixel_world) in &worlds {
+        let (Some(regions), Some(view)) = (uploaded.0.get(&entity), gpu_images.get(&pixel_world.image)) else {
+            continue;
+        };
+        let bind_group = render_device.create_bind_group(
+            Some("Region Triggers Bind Group"),
+            &pipeline.triggers_bind_group_layout,
+            &BindGroupEntries::sequential((
+                &view.texture_view,
+                regions.regions_buffer.as_entire_binding(),
+                regions.masks_buffer.as_entire_binding(),
+                regions.counts_buffer.as_entire_binding(),
+            )),
+        );
+        commands.entity(entity).insert(TriggersBindGroup(bind_group));
+    }
+}
+
+// ================================== Nodes ================================== //
+/// Counts the cells of every element inside each trigger region of every world the automata stepped this frame.
+#[derive(Default)]
+pub struct RegionTriggersNode {
+    ready: bool,
+    worlds: Option<QueryState<(Entity, &'static TriggersBindGroup)>>,
+}
+
+impl render_graph::Node for RegionTriggersNode {
+    fn update(&mut self, world: &mut World) {
+        if !self.ready {
+            let pipeline_cache = world.resource::<PipelineCache>();
+            let pipeline = world.resource::<TriggersPipeline>();
+            self.ready = [pipeline.header_pipeline, pipeline.count_pipeline].into_iter().all(|id| {
+                matches!(pipeline_cache.get_compute_pipeline_state(id), CachedPipelineState::Ok(_))
+            });
+        }
+
+        self.worlds.get_or_insert_with(|| QueryState::new(world)).update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let (true, Some(worlds)) = (self.ready, &self.worlds) else {
+            return Ok(());
+        };
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let pipeline = world.resource::<TriggersPipeline>();
+        let uploaded = world.resource::<WorldTriggerRegions>();
+
+        for (entity, triggers_bind_group) in worlds.iter_manual(world) {
+            let counted = |regions: &&TriggerRegions| regions.counted && !regions.bounds.is_empty();
+            let Some(regions) = uploaded.0.get(&entity).filter(counted) else {
+                continue;
+            };
+
+            render_context.command_encoder().clear_buffer(&regions.counts_buffer, 0, None);
+
+            let mut pass = render_context
+                .command_encoder()
+                .begin_compute_pass(&ComputePassDescriptor { label: Some("cpd-triggers") });
+
+            pass.set_bind_group(0, &triggers_bind_group.0, &[]);
+            pass.set_pipeline(pipeline_cache.get_compute_pipeline(pipeline.header_pipeline).unwrap());
+            pass.set_push_constants(
+                0,
+                bytemuck::cast_slice(&[TriggerPushConstants {
+                    region: 0,
+                    generation: regions.generation,
+                }]),
+            );
+            pass.dispatch_workgroups(1, 1, 1);
+
+            pass.set_pipeline(pipeline_cache.get_compute_pipeline(pipeline.count_pipeline).unwrap());
+            for (region, bounds) in regions.bounds.iter().enumerate() {
+                if bounds.is_empty() {
+                    continue;
+                }
+                pass.set_push_constants(
+                    0,
+                    bytemuck::cast_slice(&[TriggerPushConstants {
+                        region: region as u32,
+                        generation: regions.generation,
+                    }]),
+                );
+                let size = bounds.size();
+                pass.dispatch_workgroups(size.x.div_ceil(WORKGROUP_SIZE), size.y.div_ceil(WORKGROUP_SIZE), 1);
+            }
+        }
+
+        Ok(())
+    }
+}
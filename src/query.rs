@@ -0,0 +1,260 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    grid::CellGrid,
+    pipeline::transfer::{ReadbackFailed, ReadbackId, ReadbackRequests, RegionReadback},
+    CurrentElement, PixelWorld, PrimaryPixelWorld,
+};
+
+/// Answers [`PixelWorldQuery`] requests with [`WorldQueryResult`] events once the cells are read back.
+pub struct WorldQueryPlugin;
+
+impl Plugin for WorldQueryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingWorldQueries>()
+            .add_event::<WorldQueryResult>()
+            // readbacks arrive in First, answers are ready for the systems in Update
+            .add_systems(PreUpdate, answer_world_queries);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WorldQueryId(u64);
+
+/// First solid cell a raycast ran into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RaycastHit {
+    pub cell: IVec2,
+    /// `None` when the ray left the world, whose edges count as solid
+    pub element: Option<CurrentElement>,
+}
+
+#[derive(Clone, Debug)]
+pub enum WorldQueryAnswer {
+    /// Cells of the requested region that lie inside the world, starting at `origin`
+    Region { origin: IVec2, grid: CellGrid },
+    IsSolid(bool),
+    Raycast(Option<RaycastHit>),
+    /// The cells could not be read back, the query may be asked again
+    Failed,
+}
+
+/// Answer to a query made through [`PixelWorldQuery`].
+#[derive(Event, Clone, Debug)]
+pub struct WorldQueryResult {
+    pub id: WorldQueryId,
+    /// Entity of the [`PixelWorld`] asked about
+    pub world: Entity,
+    pub answer: WorldQueryAnswer,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum QueryKind {
+    Region,
+    IsSolid(IVec2),
+    Raycast(IVec2, IVec2),
+}
+
+struct PendingQuery {
+    id: WorldQueryId,
+    world: Entity,
+    kind: QueryKind,
+    /// `None` when nothing of the query lies inside the world, answered without a readback
+    readback: Option<ReadbackId>,
+    /// Region asked for, for queries that miss the world
+    region: IRect,
+}
+
+#[derive(Default, Resource)]
+pub struct PendingWorldQueries {
+    next_id: u64,
+    queries: Vec<PendingQuery>,
+}
+
+/// Asks about the cells of the worlds from any system, answered by a [`WorldQueryResult`]
+/// with the returned id a frame or more later. Queries made in the same frame share one readback pass.
+///
+/// The methods here ask about the [`PrimaryPixelWorld`], [`PixelWorldQuery::world`] about any other one.
+/// Positions are canvas cells like in [`crate::PixelWorldCommands`], sand and rock count as solid.
+#[derive(SystemParam)]
+pub struct PixelWorldQuery<'w, 's> {
+    pending: ResMut<'w, PendingWorldQueries>,
+    readbacks: ResMut<'w, ReadbackRequests>,
+    worlds: Query<'w, 's, (Entity, &'static PixelWorld, Has<PrimaryPixelWorld>)>,
+}
+
+impl<'w, 's> PixelWorldQuery<'w, 's> {
+    /// Asks about the [`PixelWorld`] on `world`, an entity without one answers as if every cell lay outside.
+    pub fn world(&mut self, world: Entity) -> PixelWorldReader<'_> {
+        let size = self.worlds.get(world).map_or(UVec2::ZERO, |(_, pixel_world, _)| pixel_world.size);
+        PixelWorldReader {
+            pending: &mut self.pending,
+            readbacks: &mut self.readbacks,
+            world,
+            size,
+        }
+    }
+
+    /// Asks about the [`PrimaryPixelWorld`].
+    pub fn primary(&mut self) -> PixelWorldReader<'_> {
+        let primary = self.worlds.iter().find_map(|(entity, _, primary)| primary.then_some(entity));
+        self.world(primary.unwrap_or(Entity::PLACEHOLDER))
+    }
+
+    /// Reads the cells from `rect.min` up to, but not including, `rect.max`.
+    pub fn region(&mut self, rect: IRect) -> WorldQueryId {
+        self.primary().region(rect)
+    }
+
+    /// Whether `cell` holds sand or rock, cells outside the world are solid.
+    pub fn is_solid(&mut self, cell: IVec2) -> WorldQueryId {
+        self.primary().is_solid(cell)
+    }
+
+    /// First solid cell on the line from `start` to `end`, both included.
+    pub fn raycast(&mut self, start: IVec2, end: IVec2) -> WorldQueryId {
+        self.primary().raycast(start, end)
+    }
+}
+
+/// Asks about one world, see [`PixelWorldQuery::world`].
+pub struct PixelWorldReader<'a> {
+    pending: &'a mut PendingWorldQueries,
+    readbacks: &'a mut ReadbackRequests,
+    world: Entity,
+    size: UVec2,
+}
+
+impl<'a> PixelWorldReader<'a> {
+    fn request(&mut self, kind: QueryKind, region: IRect) -> WorldQueryId {
+        let id = WorldQueryId(self.pending.next_id);
+        self.pending.next_id += 1;
+
+        let inside = region.intersect(IRect::from_corners(IVec2::ZERO, self.size.as_ivec2()));
+        let readback = (!inside.is_empty()).then(|| {
+            let rect = URect::from_corners(inside.min.as_uvec2(), inside.max.as_uvec2());
+            self.readbacks.request(self.world, rect)
+        });
+        self.pending.queries.push(PendingQuery {
+            id,
+            world: self.world,
+            kind,
+            readback,
+            region,
+        });
+        id
+    }
+
+    /// Reads the cells from `rect.min` up to, but not including, `rect.max`.
+    pub fn region(&mut self, rect: IRect) -> WorldQueryId {
+        self.request(QueryKind::Region, rect)
+    }
+
+    /// Whether `cell` holds sand or rock, cells outside the world are solid.
+    pub fn is_solid(&mut self, cell: IVec2) -> WorldQueryId {
+        self.request(QueryKind::IsSolid(cell), IRect::from_corners(cell, cell + IVec2::ONE))
+    }
+
+    /// First solid cell on the line from `start` to `end`, both included.
+    pub fn raycast(&mut self, start: IVec2, end: IVec2) -> WorldQueryId {
+        let bounds = IRect::from_corners(start.min(end), start.max(end) + IVec2::ONE);
+        self.request(QueryKind::Raycast(start, end), bounds)
+    }
+}
+
+/// Cells on the line from `start` to `end`, in order.
+fn line_cells(start: IVec2, end: IVec2) -> Vec<IVec2> {
+    let delta = (end - start).abs();
+    let step = (end - start).signum();
+    let mut error = delta.x - delta.y;
+    let mut cell = start;
+    let mut cells = vec![cell];
+    while cell != end {
+        let doubled = 2 * error;
+        if doubled > -delta.y {
+            error -= delta.y;
+            cell.x += step.x;
+        }
+        if doubled < delta.x {
+            error += delta.x;
+            cell.y += step.y;
+        }
+        cells.push(cell);
+    }
+    cells
+}
+
+/// Element at `cell` of a grid read back from `origin`, `None` outside the world.
+fn element_at(origin: IVec2, grid: &CellGrid, cell: IVec2) -> Option<CurrentElement> {
+    let local = cell - origin;
+    if local.cmplt(IVec2::ZERO).any() || local.cmpge(grid.size.as_ivec2()).any() {
+        return None;
+    }
+    Some(grid.get(local.x as u32, local.y as u32))
+}
+
+fn answer(kind: QueryKind, origin: IVec2, grid: CellGrid) -> WorldQueryAnswer {
+    match kind {
+        QueryKind::Region => WorldQueryAnswer::Region { origin, grid },
+        QueryKind::IsSolid(cell) => {
+            WorldQueryAnswer::IsSolid(element_at(origin, &grid, cell).is_none_or(|element| element.is_solid()))
+        }
+        QueryKind::Raycast(start, end) => {
+            let hit = line_cells(start, end).into_iter().find_map(|cell| {
+                let element = element_at(origin, &grid, cell);
+                element
+                    .is_none_or(|element| element.is_solid())
+                    .then_some(RaycastHit { cell, element })
+            });
+            WorldQueryAnswer::Raycast(hit)
+        }
+    }
+}
+
+fn answer_world_queries(
+    mut readbacks: EventReader<RegionReadback>,
+    mut failed: EventReader<ReadbackFailed>,
+    mut pending: ResMut<PendingWorldQueries>,
+    mut results: EventWriter<WorldQueryResult>,
+) {
+    if pending.queries.is_empty() {
+        readbacks.clear();
+        failed.clear();
+        return;
+    }
+
+    // queries entirely outside the world
+    for query in pending.queries.iter().filter(|query| query.readback.is_none()) {
+        let grid = CellGrid::new(UVec2::ZERO);
+        results.send(WorldQueryResult {
+            id: query.id,
+            world: query.world,
+            answer: answer(query.kind, query.region.min, grid),
+        });
+    }
+    pending.queries.retain(|query| query.readback.is_some());
+
+    for readback in readbacks.read() {
+        let Some(index) = pending.queries.iter().position(|query| query.readback == Some(readback.id)) else {
+            continue;
+        };
+        let query = pending.queries.remove(index);
+        results.send(WorldQueryResult {
+            id: query.id,
+            world: readback.world,
+            answer: answer(query.kind, readback.region.min.as_ivec2(), readback.to_grid()),
+        });
+    }
+
+    for failed in failed.read() {
+        let Some(index) = pending.queries.iter().position(|query| query.readback == Some(failed.id)) else {
+            continue;
+        };
+        let query = pending.queries.remove(index);
+        results.send(WorldQueryResult {
+            id: query.id,
+            world: query.world,
+            answer: WorldQueryAnswer::Failed,
+        });
+    }
+}
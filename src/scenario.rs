@@ -0,0 +1,172 @@
+use std::path::{Path, PathBuf};
+
+use crate::{cpu, grid::CellGrid, scene, worlds::PixelWorldRules, CurrentElement};
+
+/// Environment variable that makes [`ScenarioRun::assert_golden`] rewrite the goldens instead of comparing.
+pub const UPDATE_GOLDEN_VAR: &str = "UPDATE_GOLDEN";
+
+/// A small world run on the CPU stepper, to check the automata rules against expected grids and invariants.
+///
+/// ```ignore
+/// Scenario::from_ascii("sand falls", "
+///     s.
+///     ..
+/// ")
+/// .ticks(1)
+/// .run()
+/// .assert_grid("
+///     ..
+///     s.
+/// ");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    pub name: String,
+    pub grid: CellGrid,
+    pub seed: u32,
+    pub ticks: u32,
+    pub rules: PixelWorldRules,
+}
+
+impl Scenario {
+    pub fn new(name: impl Into<String>, grid: CellGrid) -> Self {
+        Self {
+            name: name.into(),
+            grid,
+            seed: 0,
+            ticks: 1,
+            rules: PixelWorldRules::default(),
+        }
+    }
+
+    /// Starts from a grid drawn with [`CurrentElement::symbol`]s, panicking on malformed art.
+    pub fn from_ascii(name: impl Into<String>, art: &str) -> Self {
+        let name = name.into();
+        let grid = CellGrid::from_ascii(art).unwrap_or_else(|error| panic!("scenario {name}: {error}"));
+        Self::new(name, grid)
+    }
+
+    /// Starts from a scene PNG, panicking if it can not be read.
+    pub fn from_png(name: impl Into<String>, path: impl AsRef<Path>) -> Self {
+        let name = name.into();
+        let path = path.as_ref();
+        let grid = scene::load_scene(path)
+            .unwrap_or_else(|error| panic!("scenario {name}: failed to load {}: {error}", path.display()));
+        Self::new(name, grid)
+    }
+
+    /// Shifts which way particles try to slide first, 0 matches the shader.
+    pub fn seed(mut self, seed: u32) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn ticks(mut self, ticks: u32) -> Self {
+        self.ticks = ticks;
+        self
+    }
+
+    /// Runs the grid by `rules` instead of the default ones.
+    pub fn rules(mut self, rules: PixelWorldRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    pub fn run(&self) -> ScenarioRun {
+        let mut grid = self.grid.clone();
+        for _ in 0..self.ticks {
+            cpu::step_with_rules(&mut grid, self.seed, self.rules);
+        }
+        ScenarioRun {
+            name: self.name.clone(),
+            initial: self.grid.clone(),
+            grid,
+        }
+    }
+}
+
+/// The outcome of [`Scenario::run`], with assertions that panic with both grids drawn out on failure.
+#[derive(Clone, Debug)]
+pub struct ScenarioRun {
+    pub name: String,
+    pub initial: CellGrid,
+    pub grid: CellGrid,
+}
+
+impl ScenarioRun {
+    fn fail(&self, message: &str) -> ! {
+        panic!(
+            "scenario {}: {message}\ninitial:\n{}final:\n{}",
+            self.name,
+            self.initial.to_ascii(),
+            self.grid.to_ascii()
+        );
+    }
+
+    /// Asserts the final grid matches one drawn with [`CurrentElement::symbol`]s.
+    pub fn assert_grid(&self, art: &str) -> &Self {
+        let expected = CellGrid::from_ascii(art).unwrap_or_else(|error| self.fail(&format!("expected grid: {error}")));
+        if self.grid != expected {
+            self.fail(&format!("final grid differs from the expected one:\n{}", expected.to_ascii()));
+        }
+        self
+    }
+
+    /// Asserts every element has as many cells as it started with.
+    pub fn assert_counts_conserved(&self) -> &Self {
+        for element in CurrentElement::ALL {
+            let (before, after) = (self.initial.count(element), self.grid.count(element));
+            if before != after {
+                self.fail(&format!("{} went from {before} to {after} cells", element.name()));
+            }
+        }
+        self
+    }
+
+    /// Asserts no cell above row `y` holds `element`.
+    pub fn assert_none_above(&self, element: CurrentElement, y: u32) -> &Self {
+        for row in 0..y.min(self.grid.size.y) {
+            if let Some(x) = (0..self.grid.size.x).find(|x| self.grid.get(*x, row) == element) {
+                self.fail(&format!("{} at ({x}, {row}), above row {y}", element.name()));
+            }
+        }
+        self
+    }
+
+    /// Asserts `check` holds for the final grid, `what` describes it in the failure.
+    pub fn assert_that(&self, what: &str, check: impl FnOnce(&CellGrid) -> bool) -> &Self {
+        if !check(&self.grid) {
+            self.fail(&format!("expected {what}"));
+        }
+        self
+    }
+
+    /// Compares the final grid with the golden snapshot `<folder>/<name>.txt`,
+    /// writing it instead when [`UPDATE_GOLDEN_VAR`] is set. A missing snapshot fails the test.
+    pub fn assert_golden(&self, folder: impl AsRef<Path>) -> &Self {
+        let path: PathBuf = folder.as_ref().join(format!("{}.txt", self.name.replace(' ', "_")));
+        let actual = self.grid.to_ascii();
+
+        if std::env::var_os(UPDATE_GOLDEN_VAR).is_some() {
+            if let Some(folder) = path.parent() {
+                std::fs::create_dir_all(folder).unwrap_or_else(|error| self.fail(&format!("{error}")));
+            }
+            std::fs::write(&path, &actual)
+                .unwrap_or_else(|error| self.fail(&format!("failed to write {}: {error}", path.display())));
+            return self;
+        }
+
+        if !path.exists() {
+            self.fail(&format!("golden {} missing, rerun with {UPDATE_GOLDEN_VAR}=1", path.display()));
+        }
+        let golden = std::fs::read_to_string(&path)
+            .unwrap_or_else(|error| self.fail(&format!("failed to read {}: {error}", path.display())));
+        if golden.replace("\r\n", "\n") != actual {
+            self.fail(&format!(
+                "final grid differs from {}, rerun with {UPDATE_GOLDEN_VAR}=1 if the change is intended:\n{golden}",
+                path.display()
+            ));
+        }
+        self
+    }
+}
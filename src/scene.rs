@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use image::{ImageError, ImageResult};
+
+use crate::{
+    actions::{Action, ActionState},
+    grid::CellGrid,
+    pipeline::transfer::{ReadbackFailed, ReadbackId, ReadbackRequests, RegionReadback, RegionWrite, RegionWrites},
+    PixelWorld, PrimaryPixelWorld,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+const SCENE_FILE: &str = "scene.png";
+
+/// Reads a scene, a PNG of element colors with one pixel per cell like the stamps.
+pub fn load_scene(path: &Path) -> ImageResult<CellGrid> {
+    let image = image::open(path)?.into_rgba8();
+    let size = UVec2::new(image.width(), image.height());
+    Ok(CellGrid::from_rgba(size, image.as_raw()))
+}
+
+/// Writes `grid` as a scene readable by [`load_scene`].
+pub fn save_scene(grid: &CellGrid, path: &Path) -> ImageResult<()> {
+    if let Some(folder) = path.parent() {
+        std::fs::create_dir_all(folder).map_err(ImageError::from)?;
+    }
+    image::save_buffer(path, &grid.to_rgba(), grid.size.x, grid.size.y, image::ColorType::Rgba8)
+}
+
+/// Saves (Ctrl+S) the whole world to `scene.png` next to `assets` and loads it back (Ctrl+O).
+pub struct ScenePlugin;
+
+impl Plugin for ScenePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SceneSave>()
+            .add_systems(Update, (save_world, write_scene, load_world));
+    }
+}
+
+/// Readback of the world waiting to be saved
+#[derive(Default, Resource)]
+struct SceneSave(Option<ReadbackId>);
+
+fn save_world(
+    actions: Res<ActionState>,
+    primary: Query<(Entity, &PixelWorld), With<PrimaryPixelWorld>>,
+    mut save: ResMut<SceneSave>,
+    mut readbacks: ResMut<ReadbackRequests>,
+) {
+    let Ok((entity, world)) = primary.get_single() else { return };
+    if actions.just_pressed(Action::SaveScene) && save.0.is_none() {
+        save.0 = Some(readbacks.request(entity, world.rect()));
+    }
+}
+
+fn write_scene(
+    mut readbacks: EventReader<RegionReadback>,
+    mut failed: EventReader<ReadbackFailed>,
+    mut save: ResMut<SceneSave>,
+) {
+    if failed.read().any(|failed| save.0 == Some(failed.id)) {
+        warn!("Failed to read the world back, the scene was not saved");
+        save.0 = None;
+    }
+    for readback in readbacks.read() {
+        if save.0 != Some(readback.id) {
+            continue;
+        }
+        save.0 = None;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let path = bevy::asset::io::file::FileAssetReader::get_base_path().join(SCENE_FILE);
+            match save_scene(&readback.to_grid(), &path) {
+                Ok(()) => info!("Saved scene {}", path.display()),
+                Err(error) => warn!("Failed to save scene {error:?}"),
+            }
+        }
+    }
+}
+
+fn load_world(
+    actions: Res<ActionState>,
+    primary: Query<(Entity, &PixelWorld), With<PrimaryPixelWorld>>,
+    mut writes: ResMut<RegionWrites>,
+) {
+    if !actions.just_pressed(Action::LoadScene) {
+        return;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let path = bevy::asset::io::file::FileAssetReader::get_base_path().join(SCENE_FILE);
+        let grid = match load_scene(&path) {
+            Ok(grid) => grid,
+            Err(error) => {
+                warn!("Failed to load scene {} {error:?}", path.display());
+                return;
+            }
+        };
+        let Ok((entity, world)) = primary.get_single() else { return };
+        let world_size = world.size;
+        if grid.size != world_size {
+            warn!("Scene is {} cells, the world {}, cutting or padding it with air", grid.size, world_size);
+        }
+        writes.0.push(RegionWrite {
+            world: entity,
+            origin: UVec2::ZERO,
+            grid: grid.resized(world_size),
+        });
+    }
+}
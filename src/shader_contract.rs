@@ -0,0 +1,108 @@
+use bevy::math::Vec4;
+
+use crate::{
+    debug_view::DebugView,
+    pipeline::{automata, debug, draw, fill, stats, triggers},
+    worlds, CurrentElement,
+};
+
+/// Shaders that match cells by their element color.
+pub const COLOR_SHADERS: [&str; 6] = [
+    "shaders/draw.wgsl",
+    "shaders/fill.wgsl",
+    "shaders/game_of_life.wgsl",
+    "shaders/stats.wgsl",
+    "shaders/debug.wgsl",
+    "shaders/triggers.wgsl",
+];
+
+/// How the Rust side lays out the `PushConstants` struct of a shader.
+#[derive(Clone, Debug)]
+pub struct PushConstantLayout {
+    /// Shader path inside `assets`
+    pub shader: &'static str,
+    pub size: usize,
+    /// Field names and their byte offsets, in declaration order
+    pub fields: Vec<(&'static str, usize)>,
+}
+
+/// Describes a `#[repr(C)]` push constant struct, expanded in the struct's own module for its private fields.
+macro_rules! push_constant_layout {
+    ($shader:literal, $ty:ty { $($field:ident),* $(,)? }) => {
+        $crate::shader_contract::PushConstantLayout {
+            shader: $shader,
+            size: std::mem::size_of::<$ty>(),
+            fields: vec![$((stringify!($field), std::mem::offset_of!($ty, $field))),*],
+        }
+    };
+}
+pub(crate) use push_constant_layout;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConstantValue {
+    U32(u32),
+    Color(Vec4),
+}
+
+/// A `const` a shader must declare with the same value as the Rust side.
+#[derive(Clone, Debug)]
+pub struct ShaderConstant {
+    /// Shader path inside `assets`
+    pub shader: &'static str,
+    pub name: String,
+    pub value: ConstantValue,
+}
+
+/// Every push constant struct shared with a shader, checked by `tests/shader_contract.rs`.
+pub fn push_constant_layouts() -> Vec<PushConstantLayout> {
+    vec![
+        automata::AutomataPushConstants::layout(),
+        draw::DrawPushConstants::layout(),
+        fill::FillPushConstants::layout(),
+        debug::DebugPushConstants::layout(),
+        triggers::TriggerPushConstants::layout(),
+    ]
+}
+
+/// Every shader constant duplicated on the Rust side, checked by `tests/shader_contract.rs`.
+pub fn shader_constants() -> Vec<ShaderConstant> {
+    let constant = |shader, name: &str, value| ShaderConstant {
+        shader,
+        name: name.to_string(),
+        value,
+    };
+
+    let mut constants = Vec::new();
+    for shader in COLOR_SHADERS {
+        for element in CurrentElement::ALL {
+            let name = format!("{}_COLOR", element.name().to_uppercase());
+            constants.push(constant(shader, &name, ConstantValue::Color(element.color())));
+        }
+    }
+
+    for (name, value) in draw::SHADER_CONSTANTS {
+        constants.push(constant("shaders/draw.wgsl", name, ConstantValue::U32(value)));
+    }
+    for (name, value) in worlds::SHADER_CONSTANTS {
+        constants.push(constant("shaders/game_of_life.wgsl", name, ConstantValue::U32(value)));
+    }
+    for (name, view) in [
+        ("VIEW_ELEMENTS", DebugView::Elements),
+        ("VIEW_CHANGES", DebugView::Changes),
+        ("VIEW_CHUNKS", DebugView::Chunks),
+    ] {
+        constants.push(constant("shaders/debug.wgsl", name, ConstantValue::U32(view.mode())));
+    }
+    constants.push(constant("shaders/debug.wgsl", "CHUNK_SIZE", ConstantValue::U32(debug::CHUNK_SIZE)));
+    constants.push(constant(
+        "shaders/stats.wgsl",
+        "COUNTERS",
+        ConstantValue::U32(stats::ELEMENT_COUNTERS as u32),
+    ));
+    constants.push(constant(
+        "shaders/triggers.wgsl",
+        "ELEMENTS",
+        ConstantValue::U32(triggers::TRIGGER_COUNTERS as u32),
+    ));
+    constants
+}
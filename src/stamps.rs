@@ -0,0 +1,262 @@
+use std::any::TypeId;
+
+use bevy::{asset::LoadedFolder, prelude::*};
+
+use crate::{actions::{Action, ActionState}, clipboard::Clipboard, grid::CellGrid, input::DrawingParams, tools::DrawTool};
+
+const STAMP_FOLDER: &str = "stamps";
+const PANEL_COLOR: Color = Color::rgba(0.1, 0.1, 0.1, 0.85);
+const BUTTON_NORMAL: Color = Color::rgb(0.15, 0.15, 0.15);
+const BUTTON_HOVERED: Color = Color::rgb(0.25, 0.25, 0.25);
+
+/// Library of reusable structures, saved as PNGs of element colors in `assets/stamps/`.
+/// Picking one from the panel (toggled with Tab) pastes it at the cursor.
+pub struct StampsPlugin;
+
+impl Plugin for StampsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, (load_stamps, spawn_stamp_panel))
+            .add_systems(
+                Update,
+                (
+                    update_stamp_library,
+                    save_clipboard_stamp,
+                    rebuild_stamp_buttons,
+                    click_stamp_button,
+                    toggle_stamp_panel,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Stamp {
+    pub name: String,
+    pub image: Handle<Image>,
+}
+
+#[derive(Resource)]
+pub struct StampLibrary {
+    folder: Handle<LoadedFolder>,
+    pub stamps: Vec<Stamp>,
+}
+
+#[derive(Component)]
+struct StampPanel;
+
+#[derive(Component)]
+struct StampButton(usize);
+
+/// Cells of a stamp image, every pixel mapped to the closest element.
+pub fn stamp_grid(image: &Image) -> Option<CellGrid> {
+    let size = image.size();
+    if image.data.len() != (size.x * size.y * 4) as usize {
+        warn!("Stamps must be 8 bit RGBA images");
+        return None;
+    }
+    Some(CellGrid::from_rgba(size, &image.data))
+}
+
+fn load_stamps(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(StampLibrary {
+        folder: asset_server.load_folder(STAMP_FOLDER),
+        stamps: Vec::new(),
+    });
+}
+
+fn update_stamp_library(
+    mut events: EventReader<AssetEvent<LoadedFolder>>,
+    folders: Res<Assets<LoadedFolder>>,
+    mut library: ResMut<StampLibrary>,
+) {
+    for event in events.read() {
+        if !event.is_loaded_with_dependencies(&library.folder) {
+            continue;
+        }
+        let Some(folder) = folders.get(&library.folder) else { continue };
+
+        let mut stamps: Vec<Stamp> = folder
+            .handles
+            .iter()
+            .filter(|handle| handle.type_id() == TypeId::of::<Image>())
+            .map(|handle| Stamp {
+                name: handle
+                    .path()
+                    .and_then(|path| path.path().file_stem())
+                    .map_or_else(|| "stamp".to_string(), |name| name.to_string_lossy().into_owned()),
+                image: handle.clone().typed::<Image>(),
+            })
+            .collect();
+        stamps.sort_by(|a, b| a.name.cmp(&b.name));
+        library.stamps = stamps;
+    }
+}
+
+// Ctrl+B adds the clipboard to the library
+fn save_clipboard_stamp(
+    actions: Res<ActionState>,
+    clipboard: Res<Clipboard>,
+    asset_server: Res<AssetServer>,
+    mut library: ResMut<StampLibrary>,
+) {
+    if !actions.just_pressed(Action::SaveStamp) {
+        return;
+    }
+    let Some(grid) = &clipboard.grid else { return };
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let folder = bevy::asset::io::file::FileAssetReader::get_base_path()
+        .join("assets")
+        .join(STAMP_FOLDER);
+    // never overwrite a stamp, whether it is in the library or only on disk
+    let name = (0..)
+        .map(|index| format!("stamp_{index}"))
+        .find(|name| {
+            #[cfg(not(target_arch = "wasm32"))]
+            if folder.join(format!("{name}.png")).exists() {
+                return false;
+            }
+            library.stamps.iter().all(|stamp| stamp.name != *name)
+        })
+        .unwrap();
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let path = folder.join(format!("{name}.png"));
+        let saved = image::save_buffer(
+            &path,
+            &grid.to_rgba(),
+            grid.size.x,
+            grid.size.y,
+            image::ColorType::Rgba8,
+        );
+        if let Err(error) = saved {
+            warn!("Failed to save stamp {error:?}");
+            return;
+        }
+        info!("Saved stamp {}", path.display());
+    }
+
+    let image = asset_server.load(format!("{STAMP_FOLDER}/{name}.png"));
+    library.stamps.push(Stamp { name, image });
+}
+
+fn spawn_stamp_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(10.),
+                    top: Val::Px(10.),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(5.)),
+                    row_gap: Val::Px(5.),
+                    display: Display::None,
+                    ..default()
+                },
+                background_color: PANEL_COLOR.into(),
+                ..default()
+            },
+            // blocks drawing while the cursor is over the panel
+            Interaction::default(),
+            StampPanel,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Stamps",
+                TextStyle {
+                    font_size: 20.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn rebuild_stamp_buttons(
+    mut commands: Commands,
+    library: Res<StampLibrary>,
+    panel: Query<Entity, With<StampPanel>>,
+    buttons: Query<Entity, With<StampButton>>,
+) {
+    if !library.is_changed() {
+        return;
+    }
+    let Ok(panel) = panel.get_single() else { return };
+
+    for button in &buttons {
+        commands.entity(button).despawn_recursive();
+    }
+    commands.entity(panel).with_children(|parent| {
+        for (index, stamp) in library.stamps.iter().enumerate() {
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(140.0),
+                            height: Val::Px(30.0),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: BUTTON_NORMAL.into(),
+                        ..default()
+                    },
+                    StampButton(index),
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        stamp.name.clone(),
+                        TextStyle {
+                            font_size: 16.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                            ..default()
+                        },
+                    ));
+                });
+        }
+    });
+}
+
+fn click_stamp_button(
+    library: Res<StampLibrary>,
+    images: Res<Assets<Image>>,
+    mut clipboard: ResMut<Clipboard>,
+    mut params: ResMut<DrawingParams>,
+    mut interaction_query: Query<(&Interaction, &mut BackgroundColor, &StampButton), Changed<Interaction>>,
+) {
+    for (interaction, mut color, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                let grid = library
+                    .stamps
+                    .get(button.0)
+                    .and_then(|stamp| images.get(&stamp.image))
+                    .and_then(stamp_grid);
+                if let Some(grid) = grid {
+                    clipboard.set_grid(grid);
+                    params.tool = DrawTool::Paste;
+                }
+            }
+            Interaction::Hovered => {
+                *color = BUTTON_HOVERED.into();
+            }
+            Interaction::None => {
+                *color = BUTTON_NORMAL.into();
+            }
+        }
+    }
+}
+
+fn toggle_stamp_panel(actions: Res<ActionState>, mut panel: Query<&mut Style, With<StampPanel>>) {
+    if !actions.just_pressed(Action::ToggleStampPanel) {
+        return;
+    }
+    for mut style in &mut panel {
+        style.display = match style.display {
+            Display::None => Display::Flex,
+            _ => Display::None,
+        };
+    }
+}
@@ -0,0 +1,357 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+
+use crate::{
+    actions::{Action, ActionState},
+    commands::WorldEdits,
+    input::{input_enabled, DrawingParams},
+    pipeline::{
+        fill::FILL_MAX_FRAMES,
+        stats::{ELEMENT_COUNTERS, ELEMENT_COUNTS_LABEL},
+        transfer::{BufferReadback, RegionWrites},
+    },
+    tools::DrawTool,
+    CurrentElement, PixelWorld, PrimaryPixelWorld,
+};
+
+const HUD_COLOR: Color = Color::rgba(0.1, 0.1, 0.1, 0.85);
+/// Frames without any edit before the element counts are expected to stay put,
+/// long enough for a running flood fill and the readback latency.
+const QUIET_FRAMES: u32 = FILL_MAX_FRAMES + 10;
+/// Count samples since the last edit before the element counts are expected to stay put,
+/// so samples counted before an edit made while the world was paused or ticking slowly are not compared.
+const QUIET_SAMPLES: u32 = 10;
+const DIAGNOSTIC_HISTORY: usize = 20;
+
+/// One diagnostic per element, indexed by [`CurrentElement::index`].
+pub const ELEMENT_COUNT_DIAGNOSTICS: [DiagnosticId; 4] = [
+    DiagnosticId::from_u128(0x6c1f_2a5e_0b7d_4e11_9a3c_5d2e_8f40_a001),
+    DiagnosticId::from_u128(0x6c1f_2a5e_0b7d_4e11_9a3c_5d2e_8f40_a002),
+    DiagnosticId::from_u128(0x6c1f_2a5e_0b7d_4e11_9a3c_5d2e_8f40_a003),
+    DiagnosticId::from_u128(0x6c1f_2a5e_0b7d_4e11_9a3c_5d2e_8f40_a004),
+];
+
+/// Counts the cells of every element of every world on the GPU in every frame the world ticked in,
+/// kept in a [`SimulationStats`] component on each [`PixelWorld`] entity rather than in a single resource,
+/// so a paused world keeps the counts of its last tick.
+/// Those of the primary world are shown in a HUD (toggled with F3) and as diagnostics.
+/// Also checks that the automata neither creates nor destroys cells.
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        for (element, id) in CurrentElement::ALL.into_iter().zip(ELEMENT_COUNT_DIAGNOSTICS) {
+            let name = format!("cells_{}", element.name().to_lowercase());
+            app.register_diagnostic(Diagnostic::new(id, name, DIAGNOSTIC_HISTORY));
+        }
+
+        app.add_systems(Startup, spawn_stats_hud.run_if(input_enabled))
+            .add_systems(
+                Update,
+                (add_world_stats, receive_counts, toggle_stats_hud.run_if(input_enabled), update_stats_hud).chain(),
+            )
+            .add_systems(PostUpdate, track_edits);
+    }
+}
+
+/// Element counts of the [`PixelWorld`] on the same entity, added along with the world.
+#[derive(Default, Component, Clone, Debug)]
+pub struct SimulationStats {
+    /// Cells per element, indexed by [`CurrentElement::index`]
+    pub counts: [u32; 4],
+    /// Cells whose color matches no element
+    pub unknown: u32,
+    /// Count readbacks received so far
+    pub samples: u64,
+    /// Whether the last check, made while nothing was being drawn, found the non-air counts unchanged
+    pub mass_conserved: Option<bool>,
+    /// Non-air cells gained (or lost, if negative) since the previous sample
+    pub mass_change: i64,
+    frames_since_edit: u32,
+    samples_since_edit: u32,
+}
+
+impl SimulationStats {
+    pub fn count(&self, element: CurrentElement) -> u32 {
+        self.counts[element.index() as usize]
+    }
+
+    /// Takes in a new sample and, once nothing was edited for a while, checks the non-air cells against the last one.
+    fn record(&mut self, counts: [u32; 4], unknown: u32) {
+        let previous_mass = self.mass();
+        let had_samples = self.samples > 0;
+        self.counts = counts;
+        self.unknown = unknown;
+        self.samples += 1;
+        self.samples_since_edit = self.samples_since_edit.saturating_add(1);
+        self.mass_change = self.mass() as i64 - previous_mass as i64;
+
+        let quiet = self.frames_since_edit >= QUIET_FRAMES && self.samples_since_edit >= QUIET_SAMPLES;
+        if had_samples && quiet {
+            self.mass_conserved = Some(self.mass_change == 0);
+        }
+    }
+
+    /// Cells that are not air.
+    pub fn mass(&self) -> u64 {
+        CurrentElement::ALL
+            .into_iter()
+            .filter(|element| *element != CurrentElement::AIR)
+            .map(|element| self.count(element) as u64)
+            .sum()
+    }
+}
+
+#[derive(Component)]
+struct StatsHud;
+
+fn add_world_stats(mut commands: Commands, worlds: Query<Entity, Added<PixelWorld>>) {
+    for world in &worlds {
+        commands.entity(world).insert(SimulationStats::default());
+    }
+}
+
+// anything drawn or pasted legitimately changes the counts
+fn track_edits(
+    params: Res<DrawingParams>,
+    writes: Res<RegionWrites>,
+    edits: Res<WorldEdits>,
+    mut worlds: Query<(Entity, &mut SimulationStats, Has<PrimaryPixelWorld>)>,
+) {
+    // the player only draws in the primary world
+    let drawn = (params.is_drawing && params.tool == DrawTool::Brush)
+        || params.is_erasing
+        || !params.shapes.is_empty()
+        || !params.strokes.is_empty()
+        || params.fill_seed.is_some();
+    for (world, mut stats, primary) in &mut worlds {
+        let edited = (primary && drawn)
+            || writes.0.iter().any(|write| write.world == world)
+            || edits.shapes(world).next().is_some();
+        if edited {
+            stats.frames_since_edit = 0;
+            stats.samples_since_edit = 0;
+        } else {
+            stats.frames_since_edit = stats.frames_since_edit.saturating_add(1);
+        }
+    }
+}
+
+fn receive_counts(
+    mut readbacks: EventReader<BufferReadback>,
+    mut worlds: Query<(&mut SimulationStats, Has<PrimaryPixelWorld>)>,
+    mut diagnostics: Diagnostics,
+) {
+    for readback in readbacks.read() {
+        if readback.label != ELEMENT_COUNTS_LABEL || readback.data.len() < ELEMENT_COUNTERS * 4 {
+            continue;
+        }
+        let Ok((mut stats, primary)) = worlds.get_mut(readback.world) else {
+            continue;
+        };
+        let mut counters = readback
+            .data
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+        let counts: [u32; 4] = std::array::from_fn(|_| counters.next().unwrap());
+        let unknown = counters.next().unwrap();
+
+        let was_conserved = stats.mass_conserved;
+        stats.record(counts, unknown);
+        if stats.mass_conserved == Some(false) && was_conserved != Some(false) {
+            warn!(
+                "Cell count of world {:?} changed by {} without any edits, the automata is not conserving mass",
+                readback.world, stats.mass_change
+            );
+        }
+
+        if !primary {
+            continue;
+        }
+        for (element, id) in CurrentElement::ALL.into_iter().zip(ELEMENT_COUNT_DIAGNOSTICS) {
+            let count = stats.count(element);
+            diagnostics.add_measurement(id, || count as f64);
+        }
+    }
+}
+
+fn spawn_stats_hud(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.),
+                    width: Val::Percent(100.),
+                    justify_content: JustifyContent::Center,
+                    display: Display::None,
+                    ..default()
+                },
+                ..default()
+            },
+            StatsHud,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(5.)),
+                        ..default()
+                    },
+                    background_color: HUD_COLOR.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font_size: 14.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                            ..default()
+                        },
+                    ));
+                });
+        });
+}
+
+fn toggle_stats_hud(actions: Res<ActionState>, mut hud: Query<&mut Style, With<StatsHud>>) {
+    if !actions.just_pressed(Action::ToggleStats) {
+        return;
+    }
+    for mut style in &mut hud {
+        style.display = match style.display {
+            Display::None => Display::Flex,
+            _ => Display::None,
+        };
+    }
+}
+
+fn update_stats_hud(
+    stats: Query<Ref<SimulationStats>, With<PrimaryPixelWorld>>,
+    hud: Query<&Children, With<StatsHud>>,
+    panels: Query<&Children>,
+    mut texts: Query<&mut Text>,
+) {
+    let Some(stats) = stats.iter().next().filter(|stats| stats.is_changed()) else {
+        return;
+    };
+
+    let mut text: Vec<String> = CurrentElement::ALL
+        .into_iter()
+        .map(|element| format!("{} {}", element.name(), stats.count(element)))
+        .collect();
+    if stats.unknown > 0 {
+        text.push(format!("Unknown {}", stats.unknown));
+    }
+    text.push(match stats.mass_conserved {
+        None => "mass: not checked yet".to_string(),
+        Some(true) => "mass: conserved".to_string(),
+        Some(false) => format!("mass: changed by {} without edits", stats.mass_change),
+    });
+    let text = text.join("   ");
+
+    for panel in hud.iter().flatten() {
+        for child in panels.get(*panel).into_iter().flatten() {
+            if let Ok(mut label) = texts.get_mut(*child) {
+                label.sections[0].value = text.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::diagnostic::DiagnosticsStore;
+
+    use super::*;
+
+    fn sand(count: u32) -> [u32; 4] {
+        let mut counts = [0; 4];
+        counts[CurrentElement::SAND.index() as usize] = count;
+        counts
+    }
+
+    fn quiet_stats() -> SimulationStats {
+        SimulationStats {
+            frames_since_edit: QUIET_FRAMES,
+            samples_since_edit: QUIET_SAMPLES,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn first_sample_is_not_checked() {
+        let mut stats = quiet_stats();
+        stats.record(sand(10), 0);
+        assert_eq!(stats.mass(), 10);
+        assert_eq!(stats.mass_change, 10);
+        assert_eq!(stats.mass_conserved, None);
+    }
+
+    #[test]
+    fn quiet_samples_check_the_mass() {
+        let mut stats = quiet_stats();
+        stats.record(sand(10), 0);
+
+        let mut moved = sand(6);
+        moved[CurrentElement::WATER.index() as usize] = 4;
+        stats.record(moved, 0);
+        assert_eq!(stats.mass_conserved, Some(true));
+
+        stats.record(sand(9), 0);
+        assert_eq!(stats.mass_change, -1);
+        assert_eq!(stats.mass_conserved, Some(false));
+    }
+
+    #[test]
+    fn air_does_not_count_as_mass() {
+        let mut stats = quiet_stats();
+        stats.record(sand(10), 0);
+        let mut counts = sand(10);
+        counts[CurrentElement::AIR.index() as usize] = 50;
+        stats.record(counts, 3);
+        assert_eq!(stats.mass_conserved, Some(true));
+        assert_eq!(stats.unknown, 3);
+    }
+
+    #[test]
+    fn edits_delay_the_check() {
+        let mut stats = quiet_stats();
+        stats.record(sand(10), 0);
+        stats.frames_since_edit = 0;
+        stats.samples_since_edit = 0;
+        stats.record(sand(20), 0);
+        assert_eq!(stats.mass_conserved, None);
+
+        // the frames went by, but the samples taken since the edit are still too few
+        stats.frames_since_edit = QUIET_FRAMES;
+        stats.record(sand(20), 0);
+        assert_eq!(stats.mass_conserved, None);
+    }
+
+    #[test]
+    fn counts_are_read_back_into_their_world() {
+        let mut app = App::new();
+        app.add_event::<BufferReadback>()
+            .init_resource::<DiagnosticsStore>()
+            .add_systems(Update, receive_counts);
+        let world = app.world.spawn(SimulationStats::default()).id();
+        let other = app.world.spawn(SimulationStats::default()).id();
+
+        let counters = sand(7).into_iter().chain([2]);
+        app.world.send_event(BufferReadback {
+            label: ELEMENT_COUNTS_LABEL,
+            world,
+            data: counters.flat_map(u32::to_le_bytes).collect(),
+        });
+        app.update();
+
+        let stats = app.world.get::<SimulationStats>(world).unwrap();
+        assert_eq!(stats.count(CurrentElement::SAND), 7);
+        assert_eq!(stats.unknown, 2);
+        assert_eq!(stats.samples, 1);
+        assert_eq!(app.world.get::<SimulationStats>(other).unwrap().samples, 0);
+    }
+}
@@ -0,0 +1,225 @@
+use bevy::prelude::*;
+
+use crate::{
+    actions::{Action, ActionState},
+    brush::{BrushMaterial, BrushMode},
+    input::{update_input_state, DrawingParams},
+    CurrentElement, PixelWorld, PrimaryPixelWorld,
+};
+
+const ELLIPSE_PREVIEW_SEGMENTS: usize = 64;
+/// Half length of the cross marking the fill bucket's target cell.
+const FILL_MARKER_SIZE: f32 = 4.0;
+const ERASER_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+
+pub struct ToolsPlugin;
+
+impl Plugin for ToolsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShapeDrag>().add_systems(
+            Update,
+            (select_tool, update_shape_drag, start_flood_fill, preview_shape, preview_brush)
+                .chain()
+                .after(update_input_state),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub enum DrawTool {
+    /// Freehand drawing while the left mouse button is held
+    #[default]
+    Brush,
+    Line,
+    Rectangle,
+    Ellipse,
+    /// Flood fills the connected region of same-element cells under the cursor
+    Fill,
+    /// Drags out a region to copy or cut
+    Select,
+    /// Places the clipboard at the cursor
+    Paste,
+}
+
+impl DrawTool {
+    /// Tools that can be picked directly, pasting is entered through the clipboard.
+    pub const SELECTABLE: [DrawTool; 6] = [
+        DrawTool::Brush,
+        DrawTool::Line,
+        DrawTool::Rectangle,
+        DrawTool::Ellipse,
+        DrawTool::Fill,
+        DrawTool::Select,
+    ];
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ShapeKind {
+    Line,
+    Rectangle,
+    Ellipse,
+}
+
+/// A shape spanning `start` to `end` in canvas pixels.
+/// Lines use `thickness` as their radius, hollow rectangles and ellipses as their outline width.
+#[derive(Clone, Debug)]
+pub struct DrawShape {
+    pub kind: ShapeKind,
+    pub start: Vec2,
+    pub end: Vec2,
+    pub thickness: f32,
+    pub hollow: bool,
+    pub element: CurrentElement,
+    pub material: BrushMaterial,
+    pub mode: BrushMode,
+}
+
+/// Canvas position the current click-drag started at, if a shape tool is being dragged.
+#[derive(Default, Resource)]
+pub struct ShapeDrag(pub Option<Vec2>);
+
+fn select_tool(actions: Res<ActionState>, mut params: ResMut<DrawingParams>) {
+    let tool = DrawTool::SELECTABLE
+        .into_iter()
+        .find(|tool| actions.just_pressed(Action::SelectTool(*tool)));
+
+    if let Some(tool) = tool {
+        params.tool = tool;
+    }
+    if actions.just_pressed(Action::ToggleHollow) {
+        params.hollow = !params.hollow;
+    }
+    if actions.just_pressed(Action::CycleMaterial) {
+        params.material = params.material.next_preset();
+    }
+    if actions.just_pressed(Action::CycleBrushMode) {
+        params.mode = params.mode.next();
+    }
+}
+
+fn update_shape_drag(
+    actions: Res<ActionState>,
+    mut drag: ResMut<ShapeDrag>,
+    mut params: ResMut<DrawingParams>,
+) {
+    let kind = match params.tool {
+        DrawTool::Brush | DrawTool::Fill | DrawTool::Select | DrawTool::Paste => {
+            drag.0 = None;
+            return;
+        }
+        DrawTool::Line => ShapeKind::Line,
+        DrawTool::Rectangle => ShapeKind::Rectangle,
+        DrawTool::Ellipse => ShapeKind::Ellipse,
+    };
+
+    if actions.just_pressed(Action::Draw) && !params.pointer_over_ui {
+        drag.0 = Some(params.mouse_pos);
+    }
+
+    if actions.just_released(Action::Draw) {
+        if let Some(start) = drag.0.take() {
+            let shape = DrawShape {
+                kind,
+                start,
+                end: params.mouse_pos,
+                thickness: params.brush_radius,
+                hollow: params.hollow,
+                element: params.element,
+                material: params.material.clone(),
+                mode: params.mode,
+            };
+            params.shapes.push(shape);
+        }
+    }
+}
+
+fn start_flood_fill(actions: Res<ActionState>, mut params: ResMut<DrawingParams>) {
+    if params.tool == DrawTool::Fill && actions.just_pressed(Action::Draw) && !params.pointer_over_ui {
+        params.fill_seed = Some(params.mouse_pos);
+    }
+}
+
+fn preview_shape(
+    mut gizmos: Gizmos,
+    drag: Res<ShapeDrag>,
+    params: Res<DrawingParams>,
+    primary: Query<(&PixelWorld, &Transform), With<PrimaryPixelWorld>>,
+) {
+    let Some(start) = drag.0 else { return };
+    let Ok((world, world_transform)) = primary.get_single() else { return };
+
+    let color = preview_color(params.element);
+    let start = world.canvas_to_world(world_transform, start);
+    let end = world.canvas_to_world(world_transform, params.mouse_pos);
+    let radius = params.brush_radius * world_transform.scale.x;
+    let center = (start + end) / 2.0;
+    let size = (end - start).abs();
+
+    match params.tool {
+        DrawTool::Brush | DrawTool::Fill | DrawTool::Select | DrawTool::Paste => {}
+        DrawTool::Line => {
+            gizmos.line_2d(start, end, color);
+            gizmos.circle_2d(start, radius, color);
+            gizmos.circle_2d(end, radius, color);
+        }
+        DrawTool::Rectangle => {
+            gizmos.rect_2d(center, 0.0, size, color);
+        }
+        DrawTool::Ellipse => {
+            let radii = size / 2.0;
+            gizmos.linestrip_2d(
+                (0..=ELLIPSE_PREVIEW_SEGMENTS).map(|i| {
+                    let angle = i as f32 / ELLIPSE_PREVIEW_SEGMENTS as f32 * std::f32::consts::TAU;
+                    center + Vec2::new(angle.cos(), angle.sin()) * radii
+                }),
+                color,
+            );
+        }
+    }
+}
+
+// the world texture stores linear colors, match how the sprite shows them
+fn preview_color(element: CurrentElement) -> Color {
+    let [r, g, b, a] = element.color().to_array();
+    Color::rgba_linear(r, g, b, a)
+}
+
+// outline of what a click would paint, in the color it would paint with
+fn preview_brush(
+    mut gizmos: Gizmos,
+    params: Res<DrawingParams>,
+    primary: Query<(&PixelWorld, &Transform), With<PrimaryPixelWorld>>,
+) {
+    if !params.cursor_in_world || params.pointer_over_ui {
+        return;
+    }
+    let Ok((world, world_transform)) = primary.get_single() else { return };
+
+    // air is as dark as the empty world, so erasing gets its own color
+    let color = if params.is_erasing {
+        ERASER_COLOR
+    } else {
+        preview_color(params.element)
+    };
+    let center = world.canvas_to_world(world_transform, params.mouse_pos);
+    let radius = params.brush_radius * world_transform.scale.x;
+
+    match params.tool {
+        DrawTool::Brush | DrawTool::Line => {
+            gizmos.circle_2d(center, radius, color);
+        }
+        // hollow shapes are outlined brush_radius wide, filled ones start at a single cell
+        DrawTool::Rectangle if params.hollow => {
+            gizmos.rect_2d(center, 0.0, Vec2::splat(radius * 2.0), color);
+        }
+        DrawTool::Ellipse if params.hollow => {
+            gizmos.circle_2d(center, radius, color);
+        }
+        DrawTool::Rectangle | DrawTool::Ellipse | DrawTool::Fill => {
+            gizmos.line_2d(center - Vec2::X * FILL_MARKER_SIZE, center + Vec2::X * FILL_MARKER_SIZE, color);
+            gizmos.line_2d(center - Vec2::Y * FILL_MARKER_SIZE, center + Vec2::Y * FILL_MARKER_SIZE, color);
+        }
+        // the selection rectangle and paste ghost show these
+        DrawTool::Select | DrawTool::Paste => {}
+    }
+}
@@ -0,0 +1,198 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    camera::{camera_controller_enabled, MAX_ZOOM, MIN_ZOOM},
+    input::{update_input_state, DrawingParams, StrokeSegment},
+    tools::DrawTool,
+    PixelWorld, PixelWorldCamera, PrimaryPixelWorld,
+};
+
+/// Fingers landing within this many seconds of each other start a pan/zoom gesture instead of drawing.
+const GESTURE_WINDOW: f32 = 0.15;
+
+/// Touch controls for the mobile build: every finger draws its own brush stroke,
+/// two fingers put down together pan and pinch-zoom the camera instead.
+pub struct TouchPlugin;
+
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TouchState>()
+            .add_systems(Update, (track_fingers, touch_gesture.run_if(camera_controller_enabled), touch_strokes).chain().after(update_input_state));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum FingerRole {
+    /// Just landed, waiting to see whether a second finger joins within [`GESTURE_WINDOW`]
+    Pending,
+    Drawing,
+    Gesture,
+    /// Left over from a gesture or landed on the UI, ignored until lifted
+    Ignored,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Finger {
+    role: FingerRole,
+    landed_at: f32,
+    /// Window position the gesture last saw the finger at
+    last_screen_pos: Vec2,
+    /// Canvas position the next stroke segment starts at
+    last_canvas_pos: Vec2,
+}
+
+#[derive(Default, Resource)]
+struct TouchState {
+    fingers: HashMap<u64, Finger>,
+    /// The two fingers of the running gesture
+    gesture: Option<[u64; 2]>,
+}
+
+fn touch_canvas_pos(
+    world: &PixelWorld,
+    world_transform: &Transform,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    position: Vec2,
+) -> Option<Vec2> {
+    camera
+        .viewport_to_world_2d(camera_transform, position)
+        .map(|world_position| world.world_to_canvas(world_transform, world_position))
+}
+
+fn track_fingers(
+    time: Res<Time>,
+    touches: Res<Touches>,
+    mut params: ResMut<DrawingParams>,
+    primary: Query<(&PixelWorld, &Transform), With<PrimaryPixelWorld>>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<PixelWorldCamera>>,
+    mut state: ResMut<TouchState>,
+) {
+    let Ok((camera, camera_transform)) = camera_q.get_single() else { return };
+    let Ok((world, world_transform)) = primary.get_single() else { return };
+    let now = time.elapsed_seconds();
+
+    for touch in touches.iter_just_released() {
+        let Some(finger) = state.fingers.remove(&touch.id()) else { continue };
+        // a tap lifted before it could start drawing still paints where it landed
+        if finger.role == FingerRole::Pending && params.tool == DrawTool::Brush {
+            params.strokes.push(StrokeSegment {
+                start: finger.last_canvas_pos,
+                end: finger.last_canvas_pos,
+            });
+        }
+    }
+    for touch in touches.iter_just_canceled() {
+        state.fingers.remove(&touch.id());
+    }
+    if let Some(ids) = state.gesture {
+        if ids.iter().any(|id| !state.fingers.contains_key(id)) {
+            state.gesture = None;
+            for id in ids {
+                if let Some(finger) = state.fingers.get_mut(&id) {
+                    finger.role = FingerRole::Ignored;
+                }
+            }
+        }
+    }
+
+    for touch in touches.iter_just_pressed() {
+        let Some(canvas_pos) = touch_canvas_pos(world, world_transform, camera, camera_transform, touch.position()) else { continue };
+        let role = if params.pointer_over_ui {
+            FingerRole::Ignored
+        } else {
+            FingerRole::Pending
+        };
+        state.fingers.insert(
+            touch.id(),
+            Finger {
+                role,
+                landed_at: now,
+                last_screen_pos: touch.position(),
+                last_canvas_pos: canvas_pos,
+            },
+        );
+    }
+
+    // two fingers waiting together become a gesture
+    if state.gesture.is_none() {
+        let pending: Vec<u64> = state
+            .fingers
+            .iter()
+            .filter(|(_, finger)| finger.role == FingerRole::Pending)
+            .map(|(id, _)| *id)
+            .collect();
+        if let [a, b, ..] = pending[..] {
+            state.gesture = Some([a, b]);
+            for id in [a, b] {
+                state.fingers.get_mut(&id).unwrap().role = FingerRole::Gesture;
+            }
+        }
+    }
+
+    for finger in state.fingers.values_mut() {
+        if finger.role == FingerRole::Pending && now - finger.landed_at >= GESTURE_WINDOW {
+            finger.role = FingerRole::Drawing;
+        }
+    }
+}
+
+fn touch_gesture(
+    touches: Res<Touches>,
+    mut state: ResMut<TouchState>,
+    mut query: Query<(&mut Transform, &mut OrthographicProjection), With<PixelWorldCamera>>,
+) {
+    let Some(ids) = state.gesture else { return };
+    let (Some(a), Some(b)) = (touches.get_pressed(ids[0]), touches.get_pressed(ids[1])) else { return };
+    let (a, b) = (a.position(), b.position());
+    let (previous_a, previous_b) = (state.fingers[&ids[0]].last_screen_pos, state.fingers[&ids[1]].last_screen_pos);
+    state.fingers.get_mut(&ids[0]).unwrap().last_screen_pos = a;
+    state.fingers.get_mut(&ids[1]).unwrap().last_screen_pos = b;
+
+    let center = (a + b) / 2.0;
+    let previous_center = (previous_a + previous_b) / 2.0;
+    let distance = a.distance(b);
+    let previous_distance = previous_a.distance(previous_b);
+
+    for (mut transform, mut ortho) in &mut query {
+        // screen coordinates grow downwards, the world upwards
+        let pan = (center - previous_center) * Vec2::new(-1.0, 1.0) * ortho.scale;
+        transform.translation += pan.extend(0.0);
+
+        if distance > 0.0 && previous_distance > 0.0 {
+            ortho.scale = (ortho.scale * previous_distance / distance).clamp(MIN_ZOOM, MAX_ZOOM);
+        }
+    }
+}
+
+fn touch_strokes(
+    touches: Res<Touches>,
+    primary: Query<(&PixelWorld, &Transform), With<PrimaryPixelWorld>>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<PixelWorldCamera>>,
+    mut state: ResMut<TouchState>,
+    mut params: ResMut<DrawingParams>,
+) {
+    let Ok((camera, camera_transform)) = camera_q.get_single() else { return };
+    let Ok((world, world_transform)) = primary.get_single() else { return };
+    if params.tool != DrawTool::Brush {
+        return;
+    }
+
+    let mut segments = Vec::new();
+    for (id, finger) in state.fingers.iter_mut() {
+        if finger.role != FingerRole::Drawing {
+            continue;
+        }
+        let Some(touch) = touches.get_pressed(*id) else { continue };
+        let Some(canvas_pos) = touch_canvas_pos(world, world_transform, camera, camera_transform, touch.position()) else { continue };
+
+        segments.push(StrokeSegment {
+            start: finger.last_canvas_pos,
+            end: canvas_pos,
+        });
+        finger.last_canvas_pos = canvas_pos;
+    }
+    if !segments.is_empty() {
+        params.strokes.extend(segments);
+    }
+}
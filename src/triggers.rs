@@ -0,0 +1,379 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+use bevy::{
+    prelude::*,
+    render::extract_component::{ExtractComponent, ExtractComponentPlugin},
+};
+
+use crate::{
+    pipeline::{
+        transfer::BufferReadback,
+        triggers::{MAX_TRIGGERS, TRIGGER_COUNTS_LABEL},
+    },
+    CurrentElement,
+};
+
+/// Watches named regions of the worlds with [`RegionTriggers`], counting their cells on the GPU in every frame
+/// the world ticked in and sending a [`RegionTriggerEvent`] whenever a [`TriggerCondition`] starts or stops holding.
+/// A paused world is not counted, its triggers keep their state until it ticks again.
+pub struct RegionTriggersPlugin;
+
+impl Plugin for RegionTriggersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RegionTriggerEvent>()
+            .add_plugins(ExtractComponentPlugin::<RegionTriggers>::default())
+            // counts arrive in First, events are ready for the systems in Update
+            .add_systems(PreUpdate, (add_trigger_states, evaluate_triggers).chain());
+    }
+}
+
+/// Source of [`RegionTriggers::generation`], unique across every world so replaced triggers never match.
+/// Starts past the 0 a fresh counts buffer holds.
+static NEXT_GENERATION: AtomicU32 = AtomicU32::new(1);
+
+fn next_generation() -> u32 {
+    NEXT_GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Cells a trigger watches.
+#[derive(Clone, Debug)]
+pub enum TriggerArea {
+    /// Cells from `min` up to, but not including, `max`
+    Rect(URect),
+    /// Cells of the `size` box at `origin` whose entry in `cells`, row by row, is set
+    Mask { origin: UVec2, size: UVec2, cells: Vec<bool> },
+}
+
+impl TriggerArea {
+    /// Box around the watched cells, clipped to a world of `world_size` cells.
+    pub fn bounds(&self, world_size: UVec2) -> URect {
+        let rect = match self {
+            TriggerArea::Rect(rect) => *rect,
+            TriggerArea::Mask { origin, size, .. } => URect::from_corners(*origin, *origin + *size),
+        };
+        rect.intersect(URect::from_corners(UVec2::ZERO, world_size))
+    }
+
+    /// Whether the cell at `cell` of the world is watched, for cells inside [`TriggerArea::bounds`].
+    pub fn contains(&self, cell: UVec2) -> bool {
+        match self {
+            TriggerArea::Rect(_) => true,
+            TriggerArea::Mask { origin, size, cells } => {
+                let local = cell - *origin;
+                cells[(local.y * size.x + local.x) as usize]
+            }
+        }
+    }
+}
+
+/// What the counted cells of a region must look like for its trigger to be active.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TriggerCondition {
+    /// More than the given number of cells of the element
+    MoreThan(CurrentElement, u32),
+    /// Fewer than the given number of cells of the element
+    FewerThan(CurrentElement, u32),
+    /// At least one cell of the element
+    Any(CurrentElement),
+    /// Nothing but air
+    Empty,
+}
+
+impl TriggerCondition {
+    /// Whether the condition holds for `counts`, indexed by [`CurrentElement::index`].
+    pub fn holds(&self, counts: &[u32; 4]) -> bool {
+        let count = |element: &CurrentElement| counts[element.index() as usize];
+        match self {
+            TriggerCondition::MoreThan(element, threshold) => count(element) > *threshold,
+            TriggerCondition::FewerThan(element, threshold) => count(element) < *threshold,
+            TriggerCondition::Any(element) => count(element) > 0,
+            TriggerCondition::Empty => CurrentElement::ALL
+                .iter()
+                .filter(|element| **element != CurrentElement::AIR)
+                .all(|element| count(element) == 0),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RegionTrigger {
+    pub name: String,
+    pub area: TriggerArea,
+    pub condition: TriggerCondition,
+}
+
+impl RegionTrigger {
+    pub fn rect(name: impl Into<String>, rect: URect, condition: TriggerCondition) -> Self {
+        Self {
+            name: name.into(),
+            area: TriggerArea::Rect(rect),
+            condition,
+        }
+    }
+
+    /// Watches the cells of the `size` box at `origin` whose entry in `cells`, row by row, is set.
+    pub fn mask(
+        name: impl Into<String>,
+        origin: UVec2,
+        size: UVec2,
+        cells: Vec<bool>,
+        condition: TriggerCondition,
+    ) -> Self {
+        assert_eq!(cells.len(), (size.x * size.y) as usize, "mask cells must cover the whole box");
+        Self {
+            name: name.into(),
+            area: TriggerArea::Mask { origin, size, cells },
+            condition,
+        }
+    }
+}
+
+/// Triggers watched on the [`crate::PixelWorld`] of the same entity whenever it ticks,
+/// at most [`MAX_TRIGGERS`] of them are counted. The [`crate::PrimaryPixelWorld`] is spawned with one.
+#[derive(Component, ExtractComponent, Clone)]
+pub struct RegionTriggers {
+    /// Shared with the render world, which gets a copy every frame
+    triggers: Arc<Vec<RegionTrigger>>,
+    /// Changed on every change, tags the counts made for this set of triggers
+    pub(crate) generation: u32,
+}
+
+impl Default for RegionTriggers {
+    fn default() -> Self {
+        Self {
+            triggers: Arc::default(),
+            generation: next_generation(),
+        }
+    }
+}
+
+impl RegionTriggers {
+    /// Adds `trigger`, replacing any trigger of the same name.
+    pub fn insert(&mut self, trigger: RegionTrigger) {
+        let triggers = Arc::make_mut(&mut self.triggers);
+        if let Some(existing) = triggers.iter_mut().find(|existing| existing.name == trigger.name) {
+            *existing = trigger;
+        } else {
+            if triggers.len() >= MAX_TRIGGERS {
+                warn!("region trigger {} ignored, only {MAX_TRIGGERS} triggers are counted", trigger.name);
+            }
+            triggers.push(trigger);
+        }
+        self.generation = next_generation();
+    }
+
+    /// Removes the trigger called `name`, returning whether there was one.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let triggers = Arc::make_mut(&mut self.triggers);
+        let len = triggers.len();
+        triggers.retain(|trigger| trigger.name != name);
+        self.generation = next_generation();
+        triggers.len() != len
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RegionTrigger> {
+        self.triggers.iter().find(|trigger| trigger.name == name)
+    }
+
+    /// Triggers in the order they are counted, those past [`MAX_TRIGGERS`] never are.
+    pub fn iter(&self) -> impl Iterator<Item = &RegionTrigger> {
+        self.triggers.iter()
+    }
+}
+
+/// Sent when the condition of a trigger starts (`active`) or stops holding.
+#[derive(Event, Clone, Debug)]
+pub struct RegionTriggerEvent {
+    /// Entity of the world the trigger watches
+    pub world: Entity,
+    pub name: String,
+    pub active: bool,
+    /// Cells per element in the region, indexed by [`CurrentElement::index`]
+    pub counts: [u32; 4],
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RegionTriggerState {
+    pub active: bool,
+    /// Cells per element in the region, indexed by [`CurrentElement::index`]
+    pub counts: [u32; 4],
+}
+
+/// Latest counts and state of every trigger of the [`RegionTriggers`] on the same entity, by name.
+/// Added along with the triggers.
+#[derive(Default, Component, Debug)]
+pub struct RegionTriggerStates(HashMap<String, RegionTriggerState>);
+
+impl RegionTriggerStates {
+    pub fn get(&self, name: &str) -> Option<&RegionTriggerState> {
+        self.0.get(name)
+    }
+
+    pub fn is_active(&self, name: &str) -> bool {
+        self.get(name).is_some_and(|state| state.active)
+    }
+}
+
+fn add_trigger_states(mut commands: Commands, worlds: Query<Entity, Added<RegionTriggers>>) {
+    for world in &worlds {
+        commands.entity(world).insert(RegionTriggerStates::default());
+    }
+}
+
+fn evaluate_triggers(
+    mut readbacks: EventReader<BufferReadback>,
+    mut worlds: Query<(Ref<RegionTriggers>, &mut RegionTriggerStates)>,
+    mut events: EventWriter<RegionTriggerEvent>,
+) {
+    for (triggers, mut states) in &mut worlds {
+        if triggers.is_changed() {
+            states.0.retain(|name, _| triggers.get(name).is_some());
+        }
+    }
+
+    for readback in readbacks.read() {
+        if readback.label != TRIGGER_COUNTS_LABEL {
+            continue;
+        }
+        let Ok((triggers, mut states)) = worlds.get_mut(readback.world) else {
+            continue;
+        };
+        let counters: Vec<u32> = readback
+            .data
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        // counts made for triggers that changed since
+        if counters.first() != Some(&triggers.generation) {
+            continue;
+        }
+
+        for (trigger, counts) in triggers.iter().zip(counters[1..].chunks_exact(4)) {
+            let counts: [u32; 4] = std::array::from_fn(|element| counts[element]);
+            let active = trigger.condition.holds(&counts);
+            let state = states.0.entry(trigger.name.clone()).or_default();
+            if state.active != active {
+                events.send(RegionTriggerEvent {
+                    world: readback.world,
+                    name: trigger.name.clone(),
+                    active,
+                    counts,
+                });
+            }
+            *state = RegionTriggerState { active, counts };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORLD_SIZE: UVec2 = UVec2::new(64, 32);
+
+    fn counts_readback(world: Entity, generation: u32, counts: &[[u32; 4]]) -> BufferReadback {
+        let counters = std::iter::once(generation).chain(counts.iter().flatten().copied());
+        BufferReadback {
+            label: TRIGGER_COUNTS_LABEL,
+            world,
+            data: counters.flat_map(u32::to_le_bytes).collect(),
+        }
+    }
+
+    fn sand(count: u32) -> [u32; 4] {
+        let mut counts = [0; 4];
+        counts[CurrentElement::SAND.index() as usize] = count;
+        counts
+    }
+
+    #[test]
+    fn rect_bounds_are_clipped_to_the_world() {
+        let inside = TriggerArea::Rect(URect::new(2, 3, 10, 12));
+        assert_eq!(inside.bounds(WORLD_SIZE), URect::new(2, 3, 10, 12));
+
+        let overhanging = TriggerArea::Rect(URect::new(60, 30, 70, 40));
+        assert_eq!(overhanging.bounds(WORLD_SIZE), URect::new(60, 30, 64, 32));
+
+        let outside = TriggerArea::Rect(URect::new(100, 100, 110, 110));
+        assert!(outside.bounds(WORLD_SIZE).is_empty());
+    }
+
+    #[test]
+    fn mask_bounds_are_clipped_and_cells_read_row_by_row() {
+        // a 3x2 box overhanging the right edge by one column
+        let area = TriggerArea::Mask {
+            origin: UVec2::new(62, 10),
+            size: UVec2::new(3, 2),
+            cells: vec![true, false, true, false, true, false],
+        };
+        assert_eq!(area.bounds(WORLD_SIZE), URect::new(62, 10, 64, 12));
+        assert!(area.contains(UVec2::new(62, 10)));
+        assert!(!area.contains(UVec2::new(63, 10)));
+        assert!(!area.contains(UVec2::new(62, 11)));
+        assert!(area.contains(UVec2::new(63, 11)));
+    }
+
+    #[test]
+    fn more_and_fewer_than_exclude_the_threshold() {
+        let more = TriggerCondition::MoreThan(CurrentElement::SAND, 5);
+        assert!(!more.holds(&sand(5)));
+        assert!(more.holds(&sand(6)));
+
+        let fewer = TriggerCondition::FewerThan(CurrentElement::SAND, 5);
+        assert!(!fewer.holds(&sand(5)));
+        assert!(fewer.holds(&sand(4)));
+    }
+
+    #[test]
+    fn any_needs_one_cell_of_the_element() {
+        let any = TriggerCondition::Any(CurrentElement::SAND);
+        assert!(!any.holds(&sand(0)));
+        assert!(any.holds(&sand(1)));
+    }
+
+    #[test]
+    fn empty_ignores_air() {
+        let mut air = [0; 4];
+        air[CurrentElement::AIR.index() as usize] = 100;
+        assert!(TriggerCondition::Empty.holds(&air));
+        assert!(TriggerCondition::Empty.holds(&[0; 4]));
+        assert!(!TriggerCondition::Empty.holds(&sand(1)));
+    }
+
+    #[test]
+    fn counts_of_a_stale_generation_are_ignored() {
+        let mut app = App::new();
+        app.add_event::<BufferReadback>().add_plugins(RegionTriggersPlugin);
+
+        let mut triggers = RegionTriggers::default();
+        triggers.insert(RegionTrigger::rect(
+            "pile",
+            URect::new(0, 0, 8, 8),
+            TriggerCondition::Any(CurrentElement::SAND),
+        ));
+        let stale = triggers.generation;
+        triggers.insert(RegionTrigger::rect(
+            "pile",
+            URect::new(0, 0, 16, 16),
+            TriggerCondition::Any(CurrentElement::SAND),
+        ));
+        let current = triggers.generation;
+        let world = app.world.spawn((triggers, RegionTriggerStates::default())).id();
+
+        app.world.send_event(counts_readback(world, stale, &[sand(3)]));
+        app.update();
+        assert!(app.world.resource::<Events<RegionTriggerEvent>>().is_empty());
+        assert!(!app.world.get::<RegionTriggerStates>(world).unwrap().is_active("pile"));
+
+        app.world.send_event(counts_readback(world, current, &[sand(3)]));
+        app.update();
+        assert!(!app.world.resource::<Events<RegionTriggerEvent>>().is_empty());
+        assert!(app.world.get::<RegionTriggerStates>(world).unwrap().is_active("pile"));
+    }
+}
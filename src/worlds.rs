@@ -0,0 +1,201 @@
+use bevy::{
+    prelude::*,
+    render::extract_component::{ExtractComponent, ExtractComponentPlugin},
+};
+
+use crate::{images, input::DrawingParams, WORKGROUP_SIZE};
+
+// keep in sync with the RULE_* constants in game_of_life.wgsl
+const RULE_SAND_SLIDES: u32 = 1;
+const RULE_WATER_SPREADS: u32 = 2;
+
+/// Constants above as named in game_of_life.wgsl, checked by `tests/shader_contract.rs`.
+pub(crate) const SHADER_CONSTANTS: [(&str, u32); 2] = [
+    ("RULE_SAND_SLIDES", RULE_SAND_SLIDES),
+    ("RULE_WATER_SPREADS", RULE_WATER_SPREADS),
+];
+
+/// Most ticks a world with a [`PixelWorldClock::tick_rate`] catches up on in one frame.
+const MAX_TICKS_PER_FRAME: u32 = 8;
+
+/// Steps every [`PixelWorld`] entity on its own clock, so one app can run several simulations side by side.
+pub struct PixelWorldsPlugin;
+
+impl Plugin for PixelWorldsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractComponentPlugin::<PixelWorld>::default())
+            .add_plugins(ExtractComponentPlugin::<PrimaryPixelWorld>::default())
+            // after the player paused or stepped in Update, before the clocks are extracted
+            .add_systems(PostUpdate, advance_clocks);
+    }
+}
+
+/// A simulation with its own texture, size, rules and clock, stepped by the automata pass.
+///
+/// Show it with [`PixelWorld::sprite`], whose `Transform` places the world. Readbacks, region writes,
+/// [`crate::PixelWorldCommands`], [`crate::PixelWorldQuery`], stats, triggers and debug views work on any world,
+/// the player's drawing and tools on the [`PrimaryPixelWorld`] spawned by [`crate::PixelWorldPlugin`].
+///
+/// Canvas positions count cells from the top left of the world, world positions are those of the
+/// sprite showing it, with one unit per cell before scaling.
+#[derive(Component, Clone, Debug, ExtractComponent)]
+pub struct PixelWorld {
+    pub image: Handle<Image>,
+    /// Cells of the world, both sides multiples of 8
+    pub size: UVec2,
+    pub rules: PixelWorldRules,
+    pub clock: PixelWorldClock,
+}
+
+impl PixelWorld {
+    /// A world of `size` cells, filled with air on its first frame.
+    pub fn new(size: UVec2, images: &mut Assets<Image>) -> Self {
+        assert!(
+            size.cmpgt(UVec2::ZERO).all() && (size % WORKGROUP_SIZE).cmpeq(UVec2::ZERO).all(),
+            "the world size {size} must be a non-zero multiple of {WORKGROUP_SIZE} cells on both sides"
+        );
+        Self {
+            image: images.add(images::create_image(size.x, size.y)),
+            size,
+            rules: PixelWorldRules::default(),
+            clock: PixelWorldClock::default(),
+        }
+    }
+
+    /// Steps the world by `rules` instead of the default ones.
+    pub fn with_rules(self, rules: PixelWorldRules) -> Self {
+        Self { rules, ..self }
+    }
+
+    /// Sprite showing the world at `transform`, one unit per cell before scaling.
+    pub fn sprite(&self, transform: Transform) -> SpriteBundle {
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(self.size.as_vec2()),
+                ..default()
+            },
+            texture: self.image.clone(),
+            transform,
+            ..default()
+        }
+    }
+
+    /// Every cell of the world.
+    pub fn rect(&self) -> URect {
+        URect::from_corners(UVec2::ZERO, self.size)
+    }
+
+    /// Workgroups a pass over every cell dispatches.
+    pub(crate) fn workgroups(&self) -> UVec2 {
+        self.size / WORKGROUP_SIZE
+    }
+
+    /// Canvas position under the world position `world_pos`, for the world placed at `transform`.
+    pub fn world_to_canvas(&self, transform: &Transform, world_pos: Vec2) -> Vec2 {
+        let local = transform.compute_affine().inverse().transform_point3(world_pos.extend(0.0));
+        local.truncate() * Vec2::new(1.0, -1.0) + self.size.as_vec2() / 2.0
+    }
+
+    /// Inverse of [`PixelWorld::world_to_canvas`].
+    pub fn canvas_to_world(&self, transform: &Transform, canvas_pos: Vec2) -> Vec2 {
+        let local = (canvas_pos - self.size.as_vec2() / 2.0) * Vec2::new(1.0, -1.0);
+        transform.transform_point(local.extend(0.0)).truncate()
+    }
+
+    /// Whether `canvas_pos` lies on a cell of the world.
+    pub fn contains(&self, canvas_pos: Vec2) -> bool {
+        canvas_pos.cmpge(Vec2::ZERO).all() && canvas_pos.cmplt(self.size.as_vec2()).all()
+    }
+}
+
+/// Which moves the automata allows a [`PixelWorld`], so worlds side by side can compare rule sets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PixelWorldRules {
+    /// Sand resting on sand slides diagonally down into air
+    pub sand_slides: bool,
+    /// Water that can not fall flows diagonally down or sideways into air
+    pub water_spreads: bool,
+}
+
+impl Default for PixelWorldRules {
+    fn default() -> Self {
+        Self {
+            sand_slides: true,
+            water_spreads: true,
+        }
+    }
+}
+
+impl PixelWorldRules {
+    /// Rules as the flags understood by the automata shader.
+    pub(crate) fn flags(&self) -> u32 {
+        let mut flags = 0;
+        if self.sand_slides {
+            flags |= RULE_SAND_SLIDES;
+        }
+        if self.water_spreads {
+            flags |= RULE_WATER_SPREADS;
+        }
+        flags
+    }
+}
+
+/// The world [`crate::PixelWorldPlugin`] spawns, the one the player draws in and every tool works on.
+/// Its clock follows the player's pause and step actions.
+#[derive(Component, Clone, Copy, Default, Debug, ExtractComponent)]
+pub struct PrimaryPixelWorld;
+
+/// When a [`PixelWorld`] ticks.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PixelWorldClock {
+    pub paused: bool,
+    /// Ticks per second, `None` ticks once every frame
+    pub tick_rate: Option<f32>,
+    /// Ticks run so far
+    pub ticks: u64,
+    /// Ticks the automata pass runs this frame
+    pub(crate) ticks_due: u32,
+    step: bool,
+    /// Fraction of a tick carried over to the next frame
+    accumulator: f32,
+}
+
+impl PixelWorldClock {
+    /// Runs a single tick next frame, even while paused.
+    pub fn step(&mut self) {
+        self.step = true;
+    }
+
+    fn advance(&mut self, delta_seconds: f32) {
+        self.ticks_due = if self.paused {
+            self.accumulator = 0.0;
+            self.step as u32
+        } else if let Some(tick_rate) = self.tick_rate {
+            self.accumulator += delta_seconds * tick_rate.max(0.0);
+            let due = self.accumulator.floor();
+            self.accumulator -= due;
+            (due as u32).min(MAX_TICKS_PER_FRAME)
+        } else {
+            1
+        };
+        self.step = false;
+        self.ticks += self.ticks_due as u64;
+    }
+}
+
+fn advance_clocks(
+    time: Res<Time>,
+    params: Res<DrawingParams>,
+    mut worlds: Query<(&mut PixelWorld, Has<PrimaryPixelWorld>)>,
+) {
+    for (mut world, primary) in &mut worlds {
+        let clock = &mut world.clock;
+        if primary {
+            clock.paused = params.paused;
+            if params.step {
+                clock.step();
+            }
+        }
+        clock.advance(time.delta_seconds());
+    }
+}
@@ -0,0 +1,266 @@
+use pixel_world::{
+    grid::CellGrid,
+    scenario::Scenario,
+    scene::save_scene,
+    worlds::PixelWorldRules,
+    CurrentElement,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const GOLDEN_FOLDER: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+/// A world of `size` with every cell picked at random, air more often than the rest.
+fn random_grid(size: (u32, u32), seed: u64) -> CellGrid {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut grid = CellGrid::new(size.into());
+    for cell in &mut grid.cells {
+        *cell = match rng.gen_range(0..6) {
+            0 => CurrentElement::SAND,
+            1 => CurrentElement::WATER,
+            2 => CurrentElement::ROCK,
+            _ => CurrentElement::AIR,
+        };
+    }
+    grid
+}
+
+#[test]
+fn sand_falls_to_the_floor() {
+    Scenario::from_ascii(
+        "sand falls to the floor",
+        "
+        .s.
+        ...
+        ...
+        ",
+    )
+    .ticks(5)
+    .run()
+    .assert_grid(
+        "
+        ...
+        ...
+        .s.
+        ",
+    );
+}
+
+#[test]
+fn sand_rests_on_rock() {
+    Scenario::from_ascii(
+        "sand rests on rock",
+        "
+        .s.
+        ...
+        .#.
+        ",
+    )
+    .ticks(5)
+    .run()
+    .assert_grid(
+        "
+        ...
+        .s.
+        .#.
+        ",
+    );
+}
+
+#[test]
+fn sand_slides_off_sand() {
+    Scenario::from_ascii(
+        "sand slides off sand",
+        "
+        .s.
+        .s.
+        ...
+        ",
+    )
+    .ticks(2)
+    .run()
+    .assert_grid(
+        "
+        ...
+        ...
+        ss.
+        ",
+    );
+}
+
+#[test]
+fn sand_does_not_slide_off_rock() {
+    Scenario::from_ascii(
+        "sand does not slide off rock",
+        "
+        .s.
+        .#.
+        ...
+        ",
+    )
+    .ticks(10)
+    .run()
+    .assert_grid(
+        "
+        .s.
+        .#.
+        ...
+        ",
+    );
+}
+
+#[test]
+fn sand_stacks_when_it_may_not_slide() {
+    Scenario::from_ascii(
+        "sand stacks when it may not slide",
+        "
+        .s.
+        .s.
+        ...
+        ",
+    )
+    .rules(PixelWorldRules {
+        sand_slides: false,
+        ..Default::default()
+    })
+    .ticks(2)
+    .run()
+    .assert_grid(
+        "
+        ...
+        .s.
+        .s.
+        ",
+    );
+}
+
+#[test]
+fn rock_never_moves() {
+    Scenario::from_ascii(
+        "rock never moves",
+        "
+        #..#
+        .##.
+        ....
+        ",
+    )
+    .ticks(10)
+    .run()
+    .assert_grid(
+        "
+        #..#
+        .##.
+        ....
+        ",
+    );
+}
+
+#[test]
+fn water_falls_and_spreads() {
+    Scenario::from_ascii(
+        "water falls and spreads",
+        "
+        ...ww...
+        ...ww...
+        ........
+        ########
+        ",
+    )
+    .ticks(20)
+    .run()
+    .assert_counts_conserved()
+    .assert_none_above(CurrentElement::WATER, 2);
+}
+
+#[test]
+fn water_only_falls_when_it_may_not_spread() {
+    Scenario::from_ascii(
+        "water only falls when it may not spread",
+        "
+        ..w..
+        .....
+        #####
+        ",
+    )
+    .rules(PixelWorldRules {
+        water_spreads: false,
+        ..Default::default()
+    })
+    .ticks(5)
+    .run()
+    .assert_grid(
+        "
+        .....
+        ..w..
+        #####
+        ",
+    );
+}
+
+#[test]
+fn sand_pile_stays_low() {
+    let mut grid = CellGrid::new((21, 20).into());
+    for y in 0..10 {
+        grid.set(10, y, CurrentElement::SAND);
+    }
+    Scenario::new("sand pile stays low", grid)
+        .ticks(100)
+        .run()
+        .assert_counts_conserved()
+        .assert_none_above(CurrentElement::SAND, 14)
+        .assert_that("sand on the floor", |grid| grid.get(10, 19) == CurrentElement::SAND);
+}
+
+#[test]
+fn random_worlds_conserve_every_element() {
+    for seed in 0..8 {
+        Scenario::new(format!("random world {seed}"), random_grid((48, 32), seed))
+            .seed(seed as u32)
+            .ticks(200)
+            .run()
+            .assert_counts_conserved();
+    }
+}
+
+#[test]
+fn scenario_from_png_matches_the_saved_grid() {
+    let grid = random_grid((16, 12), 42);
+    let path = std::env::temp_dir().join(format!("pixel_world_scenario_{}.png", std::process::id()));
+    save_scene(&grid, &path).unwrap();
+
+    let run = Scenario::from_png("scenario from png", &path).ticks(0).run();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(run.grid, grid);
+}
+
+#[test]
+fn mixed_pour_matches_golden() {
+    Scenario::from_ascii(
+        "mixed pour",
+        "
+        ..ssss....wwww..
+        ..ssss....wwww..
+        ..ssss....wwww..
+        ................
+        ......####......
+        ................
+        ................
+        #..............#
+        #..............#
+        ################
+        ",
+    )
+    .ticks(40)
+    .run()
+    .assert_counts_conserved()
+    .assert_golden(GOLDEN_FOLDER);
+}
+
+#[test]
+fn seeds_change_the_outcome_but_not_the_counts() {
+    let scenario = Scenario::new("seeded", random_grid((32, 24), 7)).ticks(60);
+    let first = scenario.clone().seed(1).run();
+    let second = scenario.seed(2).run();
+    first.assert_counts_conserved();
+    second.assert_counts_conserved();
+    assert_ne!(first.grid, second.grid);
+}
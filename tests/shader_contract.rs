@@ -0,0 +1,95 @@
+use std::{collections::HashMap, path::Path};
+
+use naga::{
+    valid::{Capabilities, ValidationFlags, Validator},
+    AddressSpace, Expression, Literal, Module, TypeInner,
+};
+use pixel_world::shader_contract::{push_constant_layouts, shader_constants, ConstantValue};
+
+const ASSETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets");
+
+fn parse(shader: &str) -> Module {
+    let path = Path::new(ASSETS).join(shader);
+    let source = std::fs::read_to_string(&path).unwrap_or_else(|error| panic!("{}: {error}", path.display()));
+    naga::front::wgsl::parse_str(&source)
+        .unwrap_or_else(|error| panic!("{}", error.emit_to_string_with_path(&source, &path.to_string_lossy())))
+}
+
+fn shaders() -> Vec<String> {
+    let mut shaders: Vec<String> = std::fs::read_dir(Path::new(ASSETS).join("shaders"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".wgsl"))
+        .map(|name| format!("shaders/{name}"))
+        .collect();
+    shaders.sort();
+    shaders
+}
+
+/// Value of the `const` called `name`, if it is a u32 or a vec4<f32> of literals.
+fn constant_value(module: &Module, name: &str) -> Option<ConstantValue> {
+    let (_, constant) = module.constants.iter().find(|(_, constant)| constant.name.as_deref() == Some(name))?;
+    let float = |handle| match module.const_expressions[handle] {
+        Expression::Literal(Literal::F32(value)) => Some(value),
+        _ => None,
+    };
+    match &module.const_expressions[constant.init] {
+        Expression::Literal(Literal::U32(value)) => Some(ConstantValue::U32(*value)),
+        Expression::Compose { components, .. } if components.len() == 4 => {
+            let components: Option<Vec<f32>> = components.iter().map(|component| float(*component)).collect();
+            Some(ConstantValue::Color(bevy::math::Vec4::from_slice(&components?)))
+        }
+        _ => None,
+    }
+}
+
+#[test]
+fn every_shader_validates() {
+    for shader in shaders() {
+        let module = parse(&shader);
+        Validator::new(ValidationFlags::all(), Capabilities::PUSH_CONSTANT)
+            .validate(&module)
+            .unwrap_or_else(|error| panic!("{shader}: {error:?}"));
+    }
+}
+
+#[test]
+fn push_constants_match_the_rust_layout() {
+    for layout in push_constant_layouts() {
+        let module = parse(layout.shader);
+        let (_, push_constants) = module
+            .global_variables
+            .iter()
+            .find(|(_, variable)| variable.space == AddressSpace::PushConstant)
+            .unwrap_or_else(|| panic!("{} declares no push constants", layout.shader));
+        let TypeInner::Struct { members, span } = &module.types[push_constants.ty].inner else {
+            panic!("{} push constants are not a struct", layout.shader);
+        };
+
+        let shader_fields: Vec<(&str, usize)> = members
+            .iter()
+            .map(|member| (member.name.as_deref().unwrap_or_default(), member.offset as usize))
+            .collect();
+        assert_eq!(shader_fields, layout.fields, "{} field names and offsets", layout.shader);
+        assert_eq!(*span as usize, layout.size, "{} push constant size", layout.shader);
+    }
+}
+
+#[test]
+fn shader_constants_match_the_rust_values() {
+    let mut modules = HashMap::new();
+    for constant in shader_constants() {
+        let module = modules.entry(constant.shader).or_insert_with(|| parse(constant.shader));
+        let value = constant_value(module, &constant.name)
+            .unwrap_or_else(|| panic!("{} declares no u32 or vec4<f32> const {}", constant.shader, constant.name));
+        match (value, constant.value) {
+            (ConstantValue::Color(shader), ConstantValue::Color(rust)) => assert!(
+                shader.abs_diff_eq(rust, 1e-6),
+                "{} {} is {shader} in the shader, {rust} in Rust",
+                constant.shader,
+                constant.name
+            ),
+            (shader, rust) => assert_eq!(shader, rust, "{} {}", constant.shader, constant.name),
+        }
+    }
+}